@@ -0,0 +1,114 @@
+//! Compares the attribute map backends, which are picked by the cargo
+//! features, so the benchmark has to be run once per backend:
+//!
+//! ```sh
+//! cargo bench --bench attributes -- --save-baseline indexmap
+//! cargo bench --bench attributes --no-default-features --features compact_str -- --baseline indexmap
+//! cargo bench --bench attributes --features small_map -- --baseline indexmap
+//! ```
+//!
+//! By default it runs on the two files in `benches/data`, which are
+//! synthetic samples written to look like game files, not the game files
+//! themselves. Point `NOITA_DATA` at an unpacked Noita `data` directory to
+//! run it on all of the game files instead.
+
+use std::{fs, path::Path};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+fn collect(dir: &Path, files: &mut Vec<String>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            collect(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "xml") {
+            let bytes = fs::read(&path).unwrap();
+            // some of the game files are not UTF-8, those are not the point
+            if let Ok(text) = String::from_utf8(bytes) {
+                if nxml_rs::parse(&text).is_ok() {
+                    files.push(text);
+                }
+            }
+        }
+    }
+}
+
+fn files() -> Vec<String> {
+    let dir = std::env::var_os("NOITA_DATA")
+        .map(Into::into)
+        .unwrap_or_else(|| Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/data"));
+    let mut files = Vec::new();
+    collect(&dir, &mut files);
+    assert!(!files.is_empty(), "no xml files in {}", dir.display());
+    files
+}
+
+fn visit(element: &nxml_rs::ElementRef, f: &mut impl FnMut(&nxml_rs::ElementRef)) {
+    f(element);
+    for child in &element.children {
+        visit(child, f);
+    }
+}
+
+fn attributes(c: &mut Criterion) {
+    let files = files();
+    let bytes = files.iter().map(|f| f.len() as u64).sum();
+
+    let mut group = c.benchmark_group("attributes");
+    group.throughput(Throughput::Bytes(bytes));
+
+    group.bench_function("parse", |b| {
+        b.iter(|| {
+            for file in &files {
+                black_box(nxml_rs::parse(black_box(file)).unwrap());
+            }
+        })
+    });
+
+    group.bench_function("to_owned", |b| {
+        let parsed = files
+            .iter()
+            .map(|f| nxml_rs::parse(f).unwrap())
+            .collect::<Vec<_>>();
+        b.iter(|| {
+            for element in &parsed {
+                black_box(element.to_owned());
+            }
+        })
+    });
+
+    group.bench_function("lookup", |b| {
+        let parsed = files
+            .iter()
+            .map(|f| nxml_rs::parse(f).unwrap())
+            .collect::<Vec<_>>();
+        b.iter(|| {
+            let mut found = 0;
+            for element in &parsed {
+                visit(element, &mut |e| {
+                    for key in ["name", "image_file", "hp", "missing"] {
+                        found += e.attr(black_box(key)).is_some() as usize;
+                    }
+                });
+            }
+            found
+        })
+    });
+
+    group.bench_function("display", |b| {
+        let parsed = files
+            .iter()
+            .map(|f| nxml_rs::parse(f).unwrap())
+            .collect::<Vec<_>>();
+        b.iter(|| {
+            for element in &parsed {
+                black_box(element.to_string());
+            }
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, attributes);
+criterion_main!(benches);
@@ -0,0 +1,208 @@
+use crate::element::{GenericElement, IntoNxmlStr, NxmlStr};
+
+/// Read-only access to an element, for writing code that works on any kind
+/// of element without caring how it stores its strings.
+///
+/// It is implemented for [`GenericElement`], so for both [`ElementRef`] and
+/// [`Element`], and can be implemented for other element-like types.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// fn scripts(element: &impl ElementAccessor) -> Vec<&str> {
+///     element
+///         .elements()
+///         .filter_map(|c| c.attr("script_source_file"))
+///         .collect()
+/// }
+///
+/// let source = r#"<Entity><LuaComponent script_source_file="a.lua"/></Entity>"#;
+///
+/// assert_eq!(scripts(&parse(source).unwrap()), ["a.lua"]);
+/// assert_eq!(scripts(&nxml!(<Entity><LuaComponent script_source_file="b.lua"/></Entity>)), ["b.lua"]);
+/// ```
+///
+/// [`ElementRef`]: crate::ElementRef
+/// [`Element`]: crate::Element
+pub trait ElementAccessor: Sized {
+    /// The name of the element.
+    fn name(&self) -> &str;
+
+    /// The text content of the element.
+    fn text_content(&self) -> &str;
+
+    /// Iterate over the attributes of the element, in the order of the
+    /// underlying map.
+    fn attributes(&self) -> impl Iterator<Item = (&str, &str)>;
+
+    /// Iterate over all child elements.
+    fn elements(&self) -> impl Iterator<Item = &Self>;
+
+    /// Get an attribute value.
+    fn attr(&self, key: &str) -> Option<&str> {
+        self.attributes().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Find the first child element with the given name.
+    fn child(&self, name: &str) -> Option<&Self> {
+        self.elements().find(|c| c.name() == name)
+    }
+
+    /// Iterate over all child elements with the given name.
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Self> {
+        self.elements().filter(move |c| c.name() == name)
+    }
+}
+
+/// Mutable counterpart of [`ElementAccessor`].
+///
+/// The new strings are taken as anything that converts into the
+/// [storage](Self::Storage) of the element.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// fn strip_debug<E>(element: &mut E)
+/// where
+///     E: ElementAccessorMut,
+///     for<'a> &'a str: IntoNxmlStr<E::Storage>,
+/// {
+///     element.remove_attr("_debug");
+///     for child in element.elements_mut() {
+///         strip_debug(child);
+///     }
+/// }
+///
+/// let mut element = nxml!(<Entity _debug="1"><Child _debug="2" a="b"/></Entity>);
+/// strip_debug(&mut element);
+///
+/// assert_eq!(element.to_string(), "<Entity><Child a=\"b\"/></Entity>");
+/// ```
+pub trait ElementAccessorMut: ElementAccessor {
+    /// The string storage that the setters convert into.
+    type Storage: NxmlStr;
+
+    /// Iterate over all child elements, mutable version.
+    fn elements_mut(&mut self) -> impl Iterator<Item = &mut Self>;
+
+    /// Set the name of the element.
+    fn set_name(&mut self, name: impl IntoNxmlStr<Self::Storage>);
+
+    /// Set the text content of the element.
+    fn set_text(&mut self, text: impl IntoNxmlStr<Self::Storage>);
+
+    /// Set an attribute value.
+    fn set_attr(
+        &mut self,
+        key: impl IntoNxmlStr<Self::Storage>,
+        value: impl IntoNxmlStr<Self::Storage>,
+    );
+
+    /// Remove an attribute, returning its value if it was there.
+    fn remove_attr(&mut self, key: &str) -> Option<<Self::Storage as NxmlStr>::Value>;
+
+    /// Add a child element at the end.
+    fn push_child(&mut self, child: Self);
+
+    /// Find the first child element with the given name, mutable version.
+    fn child_mut(&mut self, name: &str) -> Option<&mut Self> {
+        self.elements_mut().find(|c| c.name() == name)
+    }
+
+    /// Iterate over all child elements with the given name, mutable version.
+    fn children_mut<'a>(&'a mut self, name: &'a str) -> impl Iterator<Item = &'a mut Self> {
+        self.elements_mut().filter(move |c| c.name() == name)
+    }
+}
+
+impl<S: NxmlStr> ElementAccessor for GenericElement<S> {
+    fn name(&self) -> &str {
+        self.name.as_ref()
+    }
+
+    fn text_content(&self) -> &str {
+        self.text_content.as_ref()
+    }
+
+    fn attributes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attributes
+            .iter()
+            .map(|(k, v)| (k.as_ref(), v.as_ref()))
+    }
+
+    fn elements(&self) -> impl Iterator<Item = &Self> {
+        self.children.iter()
+    }
+
+    fn attr(&self, key: &str) -> Option<&str> {
+        GenericElement::attr(self, key)
+    }
+
+    fn child(&self, name: &str) -> Option<&Self> {
+        GenericElement::child(self, name)
+    }
+}
+
+impl<S: NxmlStr> ElementAccessorMut for GenericElement<S> {
+    type Storage = S;
+
+    fn elements_mut(&mut self) -> impl Iterator<Item = &mut Self> {
+        self.children.iter_mut()
+    }
+
+    fn set_name(&mut self, name: impl IntoNxmlStr<S>) {
+        self.name = name.into_name();
+    }
+
+    fn set_text(&mut self, text: impl IntoNxmlStr<S>) {
+        self.text_content = text.into_text();
+    }
+
+    fn set_attr(&mut self, key: impl IntoNxmlStr<S>, value: impl IntoNxmlStr<S>) {
+        GenericElement::set_attr(self, key, value);
+    }
+
+    fn remove_attr(&mut self, key: &str) -> Option<S::Value> {
+        GenericElement::remove_attr(self, key)
+    }
+
+    fn push_child(&mut self, child: Self) {
+        self.children.push(child);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse, Element};
+
+    fn rename<E>(element: &mut E, from: &str, to: &'static str)
+    where
+        E: ElementAccessorMut,
+        &'static str: IntoNxmlStr<E::Storage>,
+    {
+        for child in element.children_mut(from) {
+            child.set_name(to);
+        }
+    }
+
+    #[test]
+    fn same_code_for_both_storages() {
+        let source = r#"<Entity><A/><B/><A x="y"/></Entity>"#;
+        let mut borrowed = parse(source).unwrap();
+        let mut owned: Element = borrowed.to_owned();
+
+        rename(&mut owned, "A", "C");
+        rename(&mut borrowed, "A", "C");
+
+        assert_eq!(owned.to_string(), "<Entity><C/><B/><C x=\"y\"/></Entity>");
+        assert_eq!(owned, borrowed.to_owned());
+        assert_eq!(ElementAccessor::child(&owned, "C").unwrap().attr("x"), None);
+
+        let removed = ElementAccessorMut::remove_attr(&mut borrowed.children[2], "x");
+        assert_eq!(removed, Some("y"));
+        let removed = ElementAccessorMut::remove_attr(&mut owned.children[2], "x");
+        assert_eq!(removed.as_deref(), Some("y"));
+        assert_eq!(owned, borrowed.to_owned());
+    }
+}
@@ -0,0 +1,215 @@
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+use rayon::prelude::*;
+use thiserror::Error;
+
+use crate::{
+    decode::{read_to_string, Encoding, ReadError},
+    document::Document,
+    options::ParseOptions,
+    parser::NxmlError,
+};
+
+/// Parsing of many files at once, in parallel.
+///
+/// By default, every file is parsed like [`Document::read`] does, with no
+/// fallback encoding.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// # let dir = std::env::temp_dir().join(format!("nxml-rs-batch-doc-{}", std::process::id()));
+/// # std::fs::create_dir_all(dir.join("entities")).unwrap();
+/// # std::fs::write(dir.join("entities/a.xml"), "<Entity name=\"a\"/>").unwrap();
+/// # std::fs::write(dir.join("entities/b.xml"), "<Entity>\n  hello  world\n").unwrap();
+/// let files = Batch::new()
+///     .options(ParseOptions::new().lenient().warnings())
+///     .parse_dir(&dir)
+///     .unwrap();
+///
+/// let names = files
+///     .iter()
+///     .map(|file| file.path.strip_prefix(&dir).unwrap().to_str().unwrap())
+///     .collect::<Vec<_>>();
+/// assert_eq!(names, ["entities/a.xml", "entities/b.xml"]);
+///
+/// assert_eq!(files[0].result.as_ref().unwrap().root() % "name", "a");
+/// assert_eq!(files[1].warnings.len(), 2);
+/// # std::fs::remove_dir_all(&dir).unwrap();
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Batch {
+    options: ParseOptions,
+    fallback: Option<Encoding>,
+}
+
+/// A single file parsed by a [`Batch`].
+#[derive(Debug)]
+pub struct ParsedFile {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The parsed document, or why it could not be read or parsed.
+    pub result: Result<Document, FileError>,
+    /// The errors that were recovered from in
+    /// [`lenient`](ParseOptions::lenient) mode.
+    pub errors: Vec<NxmlError>,
+    /// The warnings, if [`warnings`](ParseOptions::warnings) were requested.
+    pub warnings: Vec<NxmlError>,
+}
+
+/// An error that happened while reading or parsing a file.
+///
+/// The message only names the file, the error itself is its
+/// [`source`](std::error::Error::source), so that reporters which print the
+/// whole chain do not print it twice.
+#[derive(Debug, Error)]
+#[error("Couldn't read or parse {}", path.display())]
+pub struct FileError {
+    /// The path of the file.
+    pub path: PathBuf,
+    /// The error itself.
+    pub source: ReadError,
+}
+
+impl Batch {
+    /// Same as [`Batch::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The options every file is parsed with.
+    pub fn options(mut self, options: ParseOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The encoding to decode the files that are not valid UTF-8 with, see
+    /// [`decode`](crate::decode).
+    pub fn fallback(mut self, fallback: Encoding) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Parse every `.xml` file in the directory and its subdirectories.
+    ///
+    /// The files are returned sorted by their paths. Only an error while
+    /// walking the directories is returned here, errors of the individual
+    /// files are in their [`ParsedFile::result`].
+    pub fn parse_dir(&self, dir: impl AsRef<Path>) -> io::Result<Vec<ParsedFile>> {
+        let mut paths = Vec::new();
+        collect_xml_files(dir.as_ref(), &mut paths)?;
+        paths.sort();
+        Ok(self.parse_files(paths))
+    }
+
+    /// Parse the given files, returning them in the same order.
+    pub fn parse_files<P: Into<PathBuf>>(
+        &self,
+        paths: impl IntoIterator<Item = P>,
+    ) -> Vec<ParsedFile> {
+        let paths = paths.into_iter().map(Into::into).collect::<Vec<_>>();
+        paths
+            .into_par_iter()
+            .map(|path| self.parse_file(path))
+            .collect()
+    }
+
+    fn parse_file(&self, path: PathBuf) -> ParsedFile {
+        let parsed = File::open(&path)
+            .map_err(ReadError::from)
+            .and_then(|file| Ok(read_to_string(file, self.fallback)?.0))
+            .and_then(|text| Ok(Document::parse_with(text, &self.options)?));
+        let (result, errors, warnings) = match parsed {
+            Ok((document, errors, warnings)) => (Ok(document), errors, warnings),
+            Err(source) => {
+                let error = FileError {
+                    path: path.clone(),
+                    source,
+                };
+                (Err(error), Vec::new(), Vec::new())
+            }
+        };
+        ParsedFile {
+            path,
+            result,
+            errors,
+            warnings,
+        }
+    }
+}
+
+/// Parse every `.xml` file in the directory in parallel, see
+/// [`Batch::parse_dir`].
+pub fn parse_dir(dir: impl AsRef<Path>) -> io::Result<Vec<ParsedFile>> {
+    Batch::new().parse_dir(dir)
+}
+
+/// Parse the given files in parallel, see [`Batch::parse_files`].
+pub fn parse_files<P: Into<PathBuf>>(paths: impl IntoIterator<Item = P>) -> Vec<ParsedFile> {
+    Batch::new().parse_files(paths)
+}
+
+fn collect_xml_files(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_xml_files(&path, paths)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("xml"))
+        {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_have_the_path_attached() {
+        let dir = std::env::temp_dir().join(format!("nxml-rs-batch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("bad.xml"), "<Entity></Base>").unwrap();
+        fs::write(dir.join("latin1.xml"), b"<Entity name=\"caf\xE9\"/>").unwrap();
+
+        let files = Batch::new().parse_files([
+            dir.join("bad.xml"),
+            dir.join("latin1.xml"),
+            dir.join("missing.xml"),
+        ]);
+        let files_latin1 = Batch::new()
+            .fallback(Encoding::Latin1)
+            .parse_files([dir.join("latin1.xml")]);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let errors = files
+            .iter()
+            .map(|file| file.result.as_ref().unwrap_err())
+            .collect::<Vec<_>>();
+        assert_eq!(errors[0].path, dir.join("bad.xml"));
+        assert_eq!(
+            errors[0].to_string(),
+            format!("Couldn't read or parse {}", dir.join("bad.xml").display())
+        );
+        assert_eq!(
+            std::error::Error::source(errors[0]).unwrap().to_string(),
+            "Closing element is in wrong order. Expected '</Entity>', but instead got 'Base' [1:15]"
+        );
+        assert!(matches!(
+            errors[1].source,
+            ReadError::InvalidUtf8 { valid_up_to: 17 }
+        ));
+        assert!(matches!(errors[2].source, ReadError::Io(_)));
+
+        let document = files_latin1[0].result.as_ref().unwrap();
+        assert_eq!(document.root() % "name", "café");
+    }
+}
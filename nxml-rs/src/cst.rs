@@ -0,0 +1,302 @@
+use std::{
+    borrow::Cow,
+    fmt::{self, Display},
+};
+
+use crate::{
+    element::ElementRef,
+    parser::{push_text, NxmlError},
+    reader::{Event, Reader, StartTag},
+    tokenizer::{Tokenizer, Trivia},
+};
+
+/// A lossless concrete syntax tree of a source text.
+///
+/// Unlike [`ElementRef`], it keeps everything from the source - comments,
+/// declarations, whitespace, quoting and the layout of attributes, so that
+/// [`Display`]-ing it reproduces the source exactly.
+///
+/// It is parsed with the same rules as [`parse`](crate::parse), and
+/// everything after the root element (that Noita ignores) is kept as
+/// [`CstNode::Unparsed`].
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let source = r#"<?xml version="1.0"?>
+/// <!-- the player -->
+/// <Entity name = 'player'   >
+///     <LuaComponent script_source_file="a.lua" /> <!-- todo -->
+/// </Entity>
+/// "#;
+///
+/// let cst = Cst::parse(source).unwrap();
+///
+/// assert_eq!(cst.to_string(), source);
+///
+/// let root = cst.root().unwrap();
+/// assert_eq!(root.to_element_ref(), nxml_ref!(<Entity name="'player'"><LuaComponent script_source_file="a.lua"/></Entity>));
+/// assert_eq!(root.children[3], CstNode::Comment("<!-- todo -->"));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cst<'s> {
+    /// The top-level nodes, the root element along with whatever surrounds
+    /// it.
+    pub nodes: Vec<CstNode<'s>>,
+}
+
+/// A node of the [`Cst`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CstNode<'s> {
+    /// An element, along with its content.
+    Element(CstElement<'s>),
+    /// A single text token.
+    Text {
+        /// The text as Noita sees it.
+        text: &'s str,
+        /// The text as it is in the source, e.g. with the quotes.
+        raw: &'s str,
+    },
+    /// A run of spaces, tabs and line breaks.
+    Whitespace(&'s str),
+    /// `<!-- ... -->`
+    Comment(&'s str),
+    /// `<!...>`, e.g. `<!DOCTYPE ...>`
+    Declaration(&'s str),
+    /// `<?...?>`, e.g. `<?xml version="1.0"?>`
+    ProcessingInstruction(&'s str),
+    /// Input that is ignored by the parser, e.g. anything after the root
+    /// element, or a stray `\0`.
+    Unparsed(&'s str),
+}
+
+/// An element of the [`Cst`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CstElement<'s> {
+    /// The opening tag, with spans of all of its parts.
+    pub open_tag: StartTag<'s>,
+    /// The source of the opening tag, exactly as it was.
+    pub open_tag_raw: &'s str,
+    /// Everything between the opening and closing tags.
+    pub children: Vec<CstNode<'s>>,
+    /// The source of the closing tag, exactly as it was.
+    ///
+    /// This is `None` for self-closing elements and for elements that were
+    /// not closed before the end of the file.
+    pub close_tag: Option<&'s str>,
+}
+
+impl<'s> Cst<'s> {
+    /// Parse the source into a lossless tree.
+    pub fn parse(s: &'s str) -> Result<Self, NxmlError> {
+        Builder::new(s, Reader::new(s)).build()
+    }
+
+    /// Lenient version of [`Cst::parse`], see
+    /// [`parse_lenient`](crate::parse_lenient).
+    pub fn parse_lenient(s: &'s str) -> (Self, Vec<NxmlError>) {
+        let mut builder = Builder::new(s, Reader::new(s).lenient());
+        let cst = builder.build().expect("lenient parser never errors");
+        (cst, builder.reader.take_errors())
+    }
+
+    /// The root element.
+    pub fn root(&self) -> Option<&CstElement<'s>> {
+        self.nodes.iter().find_map(|n| match n {
+            CstNode::Element(e) => Some(e),
+            _ => None,
+        })
+    }
+
+    /// The semantic view of the root element, same as what
+    /// [`parse`](crate::parse) would return.
+    pub fn to_element_ref(&self) -> Option<ElementRef<'s>> {
+        self.root().map(|root| root.to_element_ref())
+    }
+}
+
+impl<'s> CstElement<'s> {
+    /// The semantic view of this element.
+    pub fn to_element_ref(&self) -> ElementRef<'s> {
+        let mut element = ElementRef::new(self.open_tag.name);
+        for attribute in &self.open_tag.attributes {
+            element.attributes.insert(attribute.key, attribute.value);
+        }
+        let mut text_content = Cow::Borrowed("");
+        for child in &self.children {
+            match child {
+                CstNode::Element(child) => element.children.push(child.to_element_ref()),
+                CstNode::Text { text, .. } => push_text(&mut text_content, text),
+                _ => {}
+            }
+        }
+        element.text_content = text_content;
+        element
+    }
+}
+
+impl Display for Cst<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.nodes.iter().try_for_each(|node| node.fmt(f))
+    }
+}
+
+impl Display for CstNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CstNode::Element(element) => element.fmt(f),
+            CstNode::Text { raw, .. } => f.write_str(raw),
+            CstNode::Whitespace(s)
+            | CstNode::Comment(s)
+            | CstNode::Declaration(s)
+            | CstNode::ProcessingInstruction(s)
+            | CstNode::Unparsed(s) => f.write_str(s),
+        }
+    }
+}
+
+impl Display for CstElement<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.open_tag_raw)?;
+        for child in &self.children {
+            child.fmt(f)?;
+        }
+        f.write_str(self.close_tag.unwrap_or_default())
+    }
+}
+
+/// Builds the tree from the reader events, filling the gaps between them
+/// with the trivia the tokenizer skipped.
+struct Builder<'s> {
+    source: &'s str,
+    reader: Reader<'s>,
+    position: usize,
+}
+
+impl<'s> Builder<'s> {
+    fn new(source: &'s str, reader: Reader<'s>) -> Self {
+        Self {
+            source,
+            reader,
+            position: 0,
+        }
+    }
+
+    fn gap(&mut self, until: usize, nodes: &mut Vec<CstNode<'s>>) {
+        let mut tokenizer = Tokenizer::new(&self.source[self.position..until]);
+        let mut unparsed_start = None;
+        while let Some((kind, text)) = tokenizer.next_trivia() {
+            let start = self.position;
+            self.position += text.len();
+            let node = match kind {
+                Some(Trivia::Whitespace) => CstNode::Whitespace(text),
+                Some(Trivia::Comment) => CstNode::Comment(text),
+                Some(Trivia::Declaration) => CstNode::Declaration(text),
+                Some(Trivia::ProcessingInstruction) => CstNode::ProcessingInstruction(text),
+                None => {
+                    // glue the single chars back together
+                    let start = match unparsed_start {
+                        Some(start) => {
+                            nodes.pop();
+                            start
+                        }
+                        None => start,
+                    };
+                    unparsed_start = Some(start);
+                    nodes.push(CstNode::Unparsed(&self.source[start..self.position]));
+                    continue;
+                }
+            };
+            unparsed_start = None;
+            nodes.push(node);
+        }
+    }
+
+    fn build(&mut self) -> Result<Cst<'s>, NxmlError> {
+        let mut nodes = Vec::new();
+        let mut stack: Vec<CstElement<'s>> = Vec::new();
+
+        while let Some(event) = self.reader.next_event() {
+            let siblings = match stack.last_mut() {
+                Some(parent) => &mut parent.children,
+                None => &mut nodes,
+            };
+            let finished = match event? {
+                Event::StartElement(tag) => {
+                    self.gap(tag.span.start, siblings);
+                    self.position = tag.span.end;
+                    stack.push(CstElement {
+                        open_tag_raw: &self.source[tag.span.range()],
+                        open_tag: tag,
+                        children: Vec::new(),
+                        close_tag: None,
+                    });
+                    continue;
+                }
+                Event::SelfClose(tag) => {
+                    self.gap(tag.span.start, siblings);
+                    self.position = tag.span.end;
+                    CstElement {
+                        open_tag_raw: &self.source[tag.span.range()],
+                        open_tag: tag,
+                        children: Vec::new(),
+                        close_tag: None,
+                    }
+                }
+                Event::Text { text, span } => {
+                    self.gap(span.start, siblings);
+                    siblings.push(CstNode::Text {
+                        text,
+                        raw: &self.source[span.range()],
+                    });
+                    self.position = span.end;
+                    continue;
+                }
+                Event::EndElement { span, .. } => {
+                    if let Some(span) = span {
+                        self.gap(span.start, siblings);
+                        self.position = span.end;
+                    }
+                    let Some(mut finished) = stack.pop() else {
+                        continue;
+                    };
+                    finished.close_tag = span.map(|span| &self.source[span.range()]);
+                    finished
+                }
+            };
+            match stack.last_mut() {
+                Some(parent) => parent.children.push(CstNode::Element(finished)),
+                None => nodes.push(CstNode::Element(finished)),
+            }
+        }
+        self.gap(self.source.len(), &mut nodes);
+
+        Ok(Cst { nodes })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrips_broken_input() {
+        for source in [
+            "",
+            "  <!-- only a comment -->",
+            "<a b=c d \"e\"=/ >text \"quoted text\" <b\0/> </c> <!-- x --></a> trailing <x/>",
+            "<a><b><c x=\"unterminated",
+            "<a><!-",
+            "junk <a/>",
+            "<a>\0 </a>",
+        ] {
+            let (cst, _) = Cst::parse_lenient(source);
+            assert_eq!(cst.to_string(), source);
+            assert_eq!(
+                cst.to_element_ref().unwrap(),
+                crate::parse_lenient(source).0,
+                "{source:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use crate::element::{GenericElement, NxmlStr};
+
+/// A read-only cursor over an element tree, which unlike the element itself
+/// can move up to the parent and sideways to the siblings.
+///
+/// The moving methods return whether the cursor moved, and stay in place
+/// if there is nowhere to move.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let element = nxml_ref!(<Entity><A/><B><C/></B><D/></Entity>);
+///
+/// let mut cursor = element.cursor();
+/// assert!(cursor.first_child());
+/// assert!(cursor.next_sibling());
+/// assert!(cursor.first_child());
+/// assert_eq!(cursor.element().name, "C");
+/// assert_eq!(cursor.path(), [1, 0]);
+/// assert_eq!(cursor.describe_path(), "Entity/B/C");
+///
+/// assert!(!cursor.next_sibling());
+/// assert!(cursor.parent());
+/// assert!(cursor.prev_sibling());
+/// assert_eq!(cursor.element().name, "A");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cursor<'a, S: NxmlStr> {
+    /// The ancestors of the current element, starting with the root.
+    ancestors: Vec<&'a GenericElement<S>>,
+    current: &'a GenericElement<S>,
+    path: Vec<usize>,
+}
+
+impl<'a, S: NxmlStr> Cursor<'a, S> {
+    /// The element the cursor is at.
+    pub fn element(&self) -> &'a GenericElement<S> {
+        self.current
+    }
+
+    /// The path of child indices from the root to the element the cursor is
+    /// at, same as the ones used by [`Editor`](crate::Editor).
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+
+    /// How deep the cursor is, with the root being at depth 0.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Describe the path of the cursor with the element names, see
+    /// [`describe_path`](GenericElement::describe_path).
+    pub fn describe_path(&self) -> String {
+        let root = self.ancestors.first().unwrap_or(&self.current);
+        root.describe_path(&self.path)
+            .expect("the cursor path is always valid")
+    }
+
+    /// Move to the parent element.
+    pub fn parent(&mut self) -> bool {
+        let Some(parent) = self.ancestors.pop() else {
+            return false;
+        };
+        self.current = parent;
+        self.path.pop();
+        true
+    }
+
+    /// Move to the child element at the index.
+    pub fn child(&mut self, index: usize) -> bool {
+        let Some(child) = self.current.children.get(index) else {
+            return false;
+        };
+        self.ancestors.push(self.current);
+        self.current = child;
+        self.path.push(index);
+        true
+    }
+
+    /// Move to the first child element.
+    pub fn first_child(&mut self) -> bool {
+        self.child(0)
+    }
+
+    /// Move to the last child element.
+    pub fn last_child(&mut self) -> bool {
+        match self.current.children.len() {
+            0 => false,
+            len => self.child(len - 1),
+        }
+    }
+
+    fn sibling(&mut self, index: Option<usize>) -> bool {
+        let (Some(parent), Some(index)) = (self.ancestors.last(), index) else {
+            return false;
+        };
+        let Some(sibling) = parent.children.get(index) else {
+            return false;
+        };
+        self.current = sibling;
+        *self.path.last_mut().expect("not at the root") = index;
+        true
+    }
+
+    /// Move to the next sibling element.
+    pub fn next_sibling(&mut self) -> bool {
+        self.sibling(self.path.last().map(|i| i + 1))
+    }
+
+    /// Move to the previous sibling element.
+    pub fn prev_sibling(&mut self) -> bool {
+        self.sibling(self.path.last().and_then(|i| i.checked_sub(1)))
+    }
+}
+
+/// A mutable cursor over an element tree, which can also change the tree
+/// around the element it is at.
+///
+/// The moving methods work the same as the ones of [`Cursor`].
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let mut element = nxml!(<Entity><A/><B/></Entity>);
+///
+/// let mut cursor = element.cursor_mut();
+/// cursor.first_child();
+/// cursor.insert_after(Element::new("C")).unwrap();
+/// cursor.next_sibling();
+/// cursor.element_mut().set_attr("x", 1);
+///
+/// cursor.next_sibling();
+/// let removed = cursor.remove().unwrap();
+/// assert_eq!(removed.name, "B");
+/// assert_eq!(cursor.path(), [1]);
+///
+/// assert_eq!(element.to_string(), "<Entity><A/><C x=\"1\"/></Entity>");
+/// ```
+#[derive(Debug)]
+pub struct CursorMut<'a, S: NxmlStr> {
+    root: &'a mut GenericElement<S>,
+    path: Vec<usize>,
+}
+
+impl<'a, S: NxmlStr> CursorMut<'a, S> {
+    /// The element the cursor is at.
+    pub fn element(&self) -> &GenericElement<S> {
+        self.root
+            .at_path(&self.path)
+            .expect("the cursor path is always valid")
+    }
+
+    /// The element the cursor is at, mutable version.
+    pub fn element_mut(&mut self) -> &mut GenericElement<S> {
+        self.root
+            .at_path_mut(&self.path)
+            .expect("the cursor path is always valid")
+    }
+
+    /// The path of child indices from the root to the element the cursor is
+    /// at, see [`Cursor::path`].
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+
+    /// How deep the cursor is, with the root being at depth 0.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// Describe the path of the cursor with the element names, see
+    /// [`describe_path`](GenericElement::describe_path).
+    pub fn describe_path(&self) -> String {
+        self.root
+            .describe_path(&self.path)
+            .expect("the cursor path is always valid")
+    }
+
+    /// A read-only cursor at the same position.
+    pub fn as_cursor(&self) -> Cursor<'_, S> {
+        let mut cursor = self.root.cursor();
+        for &index in &self.path {
+            cursor.child(index);
+        }
+        cursor
+    }
+
+    /// Move to the parent element.
+    pub fn parent(&mut self) -> bool {
+        self.path.pop().is_some()
+    }
+
+    /// Move to the child element at the index.
+    pub fn child(&mut self, index: usize) -> bool {
+        if index >= self.element().children.len() {
+            return false;
+        }
+        self.path.push(index);
+        true
+    }
+
+    /// Move to the first child element.
+    pub fn first_child(&mut self) -> bool {
+        self.child(0)
+    }
+
+    /// Move to the last child element.
+    pub fn last_child(&mut self) -> bool {
+        match self.element().children.len() {
+            0 => false,
+            len => self.child(len - 1),
+        }
+    }
+
+    /// The parent of the element the cursor is at, and the index of the
+    /// element among its children.
+    fn parent_and_index(&mut self) -> Option<(&mut GenericElement<S>, usize)> {
+        let (&index, parent_path) = self.path.split_last()?;
+        let parent = self
+            .root
+            .at_path_mut(parent_path)
+            .expect("the cursor path is always valid");
+        Some((parent, index))
+    }
+
+    fn sibling(&mut self, index: Option<usize>) -> bool {
+        let Some(((parent, _), index)) = self.parent_and_index().zip(index) else {
+            return false;
+        };
+        if index >= parent.children.len() {
+            return false;
+        }
+        *self.path.last_mut().expect("not at the root") = index;
+        true
+    }
+
+    /// Move to the next sibling element.
+    pub fn next_sibling(&mut self) -> bool {
+        self.sibling(self.path.last().map(|i| i + 1))
+    }
+
+    /// Move to the previous sibling element.
+    pub fn prev_sibling(&mut self) -> bool {
+        self.sibling(self.path.last().and_then(|i| i.checked_sub(1)))
+    }
+
+    /// Insert a sibling element before the one the cursor is at, keeping
+    /// the cursor at the same element.
+    ///
+    /// Returns the element back if the cursor is at the root, which can have
+    /// no siblings.
+    pub fn insert_before(&mut self, element: GenericElement<S>) -> Result<(), GenericElement<S>> {
+        let Some((parent, index)) = self.parent_and_index() else {
+            return Err(element);
+        };
+        parent.children.insert(index, element);
+        *self.path.last_mut().expect("not at the root") += 1;
+        Ok(())
+    }
+
+    /// Insert a sibling element after the one the cursor is at, keeping the
+    /// cursor at the same element.
+    ///
+    /// Returns the element back if the cursor is at the root, which can have
+    /// no siblings.
+    pub fn insert_after(&mut self, element: GenericElement<S>) -> Result<(), GenericElement<S>> {
+        let Some((parent, index)) = self.parent_and_index() else {
+            return Err(element);
+        };
+        parent.children.insert(index + 1, element);
+        Ok(())
+    }
+
+    /// Add a child element at the end of the children of the element the
+    /// cursor is at.
+    pub fn push_child(&mut self, element: GenericElement<S>) {
+        self.element_mut().children.push(element);
+    }
+
+    /// Replace the element the cursor is at, returning the old one.
+    pub fn replace(&mut self, element: GenericElement<S>) -> GenericElement<S> {
+        std::mem::replace(self.element_mut(), element)
+    }
+
+    /// Remove the element the cursor is at, moving the cursor to the next
+    /// sibling, or to the previous one if it was the last, or to the parent
+    /// if it was the only child.
+    ///
+    /// Returns `None` if the cursor is at the root, which cannot be removed.
+    pub fn remove(&mut self) -> Option<GenericElement<S>> {
+        let (parent, index) = self.parent_and_index()?;
+        let removed = parent.children.remove(index);
+        let siblings = parent.children.len();
+        if siblings == 0 {
+            self.path.pop();
+        } else if index == siblings {
+            *self.path.last_mut().expect("not at the root") = index - 1;
+        }
+        Some(removed)
+    }
+}
+
+impl<S: NxmlStr> GenericElement<S> {
+    /// A read-only cursor at this element, see [`Cursor`].
+    pub fn cursor(&self) -> Cursor<'_, S> {
+        Cursor {
+            ancestors: Vec::new(),
+            current: self,
+            path: Vec::new(),
+        }
+    }
+
+    /// A mutable cursor at this element, see [`CursorMut`].
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, S> {
+        CursorMut {
+            root: self,
+            path: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, ElementRef};
+
+    #[test]
+    fn edits_at_the_root_and_the_edges() {
+        let mut element = parse("<r><a/><b/></r>").unwrap();
+
+        let mut cursor = element.cursor_mut();
+        assert!(!cursor.parent());
+        assert!(!cursor.next_sibling());
+        assert!(cursor.remove().is_none());
+        assert!(cursor.insert_before(ElementRef::new("x")).is_err());
+
+        assert!(cursor.last_child());
+        cursor.insert_before(ElementRef::new("c")).unwrap();
+        assert_eq!(cursor.path(), [2]);
+        assert_eq!(cursor.describe_path(), "r/b");
+
+        assert_eq!(cursor.remove().unwrap().name, "b");
+        assert_eq!(cursor.element().name, "c");
+        assert_eq!(cursor.replace(ElementRef::new("d")).name, "c");
+        assert_eq!(cursor.as_cursor().element().name, "d");
+
+        cursor.push_child(ElementRef::new("e"));
+        assert!(cursor.first_child());
+        assert_eq!(cursor.remove().unwrap().name, "e");
+        assert_eq!(cursor.path(), [1]);
+
+        assert_eq!(element.to_string(), "<r><a/><d/></r>");
+    }
+}
@@ -0,0 +1,220 @@
+use std::{borrow::Cow, io::Read};
+
+use thiserror::Error;
+
+use crate::{
+    element::{Element, ElementRef},
+    parser::{parse, NxmlError},
+};
+
+/// A text encoding of the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// UTF-8, which is what Noita and most of the mods use.
+    Utf8,
+    /// Windows-1252, which is what older mods made with some Windows text
+    /// editors sometimes end up in.
+    Windows1252,
+    /// ISO-8859-1, every byte is the Unicode code point of the same value.
+    Latin1,
+}
+
+/// What was detected about the source when decoding it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Detected {
+    /// The encoding the source was decoded with.
+    pub encoding: Encoding,
+    /// Whether the source started with a UTF-8 byte order mark, which was
+    /// stripped.
+    pub bom: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum ReadError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("Invalid UTF-8 after {valid_up_to} bytes")]
+    InvalidUtf8 { valid_up_to: usize },
+    #[error(transparent)]
+    Parse(#[from] NxmlError),
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Windows-1252 differs from Latin-1 only in the 0x80..0xA0 range.
+/// The five undefined bytes map to the same code points, like browsers do.
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+impl Encoding {
+    fn decode(self, bytes: &[u8]) -> Cow<'_, str> {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes),
+            Encoding::Windows1252 => bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => WINDOWS_1252[b as usize - 0x80],
+                    _ => b as char,
+                })
+                .collect(),
+            Encoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+        }
+    }
+}
+
+/// Decode the source bytes, stripping the UTF-8 BOM if there is one.
+///
+/// The bytes are expected to be UTF-8, and if they are not, they are decoded
+/// with the `fallback` encoding, or an error is returned if there is none.
+/// A fallback of [`Encoding::Utf8`] replaces invalid sequences with `U+FFFD`.
+///
+/// The text is only copied if the fallback was used.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let (text, detected) = decode(b"\xEF\xBB\xBF<Entity name=\"caf\xE9\"/>", Some(Encoding::Windows1252)).unwrap();
+///
+/// assert_eq!(text, "<Entity name=\"café\"/>");
+/// assert_eq!(detected, Detected { encoding: Encoding::Windows1252, bom: true });
+/// ```
+pub fn decode(
+    bytes: &[u8],
+    fallback: Option<Encoding>,
+) -> Result<(Cow<'_, str>, Detected), ReadError> {
+    let (bytes, bom) = match bytes.strip_prefix(UTF8_BOM) {
+        Some(bytes) => (bytes, true),
+        None => (bytes, false),
+    };
+    let (text, encoding) = match (std::str::from_utf8(bytes), fallback) {
+        (Ok(text), _) => (Cow::Borrowed(text), Encoding::Utf8),
+        (Err(_), Some(fallback)) => (fallback.decode(bytes), fallback),
+        (Err(e), None) => {
+            return Err(ReadError::InvalidUtf8 {
+                valid_up_to: e.valid_up_to() + if bom { UTF8_BOM.len() } else { 0 },
+            })
+        }
+    };
+    Ok((text, Detected { encoding, bom }))
+}
+
+/// Parse the source bytes, see [`decode`].
+///
+/// If the bytes need to be transcoded, the decoded text is put into the
+/// `buffer`, which the resulting element then borrows from - use
+/// [`parse_bytes_owned`] to avoid that.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let bytes = b"\xEF\xBB\xBF<Entity>\r\n\t<Sprite image_file=\"data/a.png\"/>\r\n</Entity>\r\n";
+/// let mut buffer = String::new();
+///
+/// let (element, detected) = parse_bytes(bytes, None, &mut buffer).unwrap();
+///
+/// assert_eq!(&element / "Sprite" % "image_file", "data/a.png");
+/// assert!(detected.bom);
+/// ```
+pub fn parse_bytes<'b>(
+    bytes: &'b [u8],
+    fallback: Option<Encoding>,
+    buffer: &'b mut String,
+) -> Result<(ElementRef<'b>, Detected), ReadError> {
+    let (text, detected) = decode(bytes, fallback)?;
+    let text = match text {
+        Cow::Borrowed(text) => text,
+        Cow::Owned(text) => {
+            *buffer = text;
+            buffer.as_str()
+        }
+    };
+    Ok((parse(text)?, detected))
+}
+
+/// Same as [`parse_bytes`], but returns an owned [`Element`].
+pub fn parse_bytes_owned(
+    bytes: &[u8],
+    fallback: Option<Encoding>,
+) -> Result<(Element, Detected), ReadError> {
+    let (text, detected) = decode(bytes, fallback)?;
+    let element = parse(&text)?.to_owned();
+    Ok((element, detected))
+}
+
+/// Read everything from the reader and parse it, see [`decode`].
+///
+/// The decoded text is put into the `buffer`, which the resulting element
+/// then borrows from - use [`parse_reader_owned`] to avoid that.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let file: &[u8] = b"<Entity name=\"\x93quoted\x94\"/>";
+/// let mut buffer = String::new();
+///
+/// let (element, detected) = parse_reader(file, Some(Encoding::Windows1252), &mut buffer).unwrap();
+///
+/// assert_eq!(&element % "name", "“quoted”");
+/// assert_eq!(detected.encoding, Encoding::Windows1252);
+/// ```
+pub fn parse_reader(
+    reader: impl Read,
+    fallback: Option<Encoding>,
+    buffer: &mut String,
+) -> Result<(ElementRef<'_>, Detected), ReadError> {
+    let (text, detected) = read_to_string(reader, fallback)?;
+    *buffer = text;
+    Ok((parse(buffer)?, detected))
+}
+
+/// Read everything from the reader and decode it, see [`decode`].
+pub(crate) fn read_to_string(
+    mut reader: impl Read,
+    fallback: Option<Encoding>,
+) -> Result<(String, Detected), ReadError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let (text, detected) = decode(&bytes, fallback)?;
+    let text = match text {
+        // avoid copying the text if it is already valid
+        Cow::Borrowed(text) => {
+            let start = text.as_ptr() as usize - bytes.as_ptr() as usize;
+            bytes.drain(..start);
+            String::from_utf8(bytes).expect("just validated")
+        }
+        Cow::Owned(text) => text,
+    };
+    Ok((text, detected))
+}
+
+/// Same as [`parse_reader`], but returns an owned [`Element`].
+pub fn parse_reader_owned(
+    reader: impl Read,
+    fallback: Option<Encoding>,
+) -> Result<(Element, Detected), ReadError> {
+    let mut buffer = String::new();
+    let (element, detected) = parse_reader(reader, fallback, &mut buffer)?;
+    Ok((element.to_owned(), detected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_utf8_without_fallback() {
+        let err = decode(b"\xEF\xBB\xBF<a b=\"\xFF\"/>", None).unwrap_err();
+        assert!(matches!(err, ReadError::InvalidUtf8 { valid_up_to: 9 }));
+    }
+
+    #[test]
+    fn latin1_and_windows1252_differ() {
+        let bytes = b"<a b=\"\x80\xE9\"/>\xFF";
+        let (latin1, _) = decode(bytes, Some(Encoding::Latin1)).unwrap();
+        let (windows, _) = decode(bytes, Some(Encoding::Windows1252)).unwrap();
+        assert_eq!(latin1, "<a b=\"\u{80}é\"/>ÿ");
+        assert_eq!(windows, "<a b=\"€é\"/>ÿ");
+    }
+}
@@ -0,0 +1,307 @@
+use std::{
+    fmt::{self, Display},
+    ops::Range,
+};
+
+use crate::{
+    parser::{NxmlError, Severity},
+    tokenizer::Position,
+};
+
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+const TAB_WIDTH: usize = 4;
+
+impl NxmlError {
+    /// Render the error along with the lines of the source it is about,
+    /// pointing at exactly what is wrong.
+    ///
+    /// The `source` must be the text that was parsed.
+    ///
+    /// With the `miette` feature enabled, [`NxmlError`] also implements
+    /// `miette::Diagnostic` with the same labels, so it can be reported with
+    /// `miette::Report::new(err).with_source_code(source)` instead.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let source = "<Entity>\n    <Base file=\"a.xml\">\n</Entity>\n";
+    ///
+    /// let err = parse(source).unwrap_err();
+    ///
+    /// assert_eq!(err.render(source).name("entities/a.xml").to_string(), "\
+    /// error[NXML0003]: Closing element is in wrong order. Expected '</Base>', but instead got 'Entity'
+    ///  --> entities/a.xml:3:1
+    ///   |
+    /// 2 |     <Base file=\"a.xml\">
+    ///   |     ------------------- opening tag here
+    /// 3 | </Entity>
+    ///   | ^^^^^^^^
+    ///   = help: Noita closes the current element anyway, no matter the name, and the '>' of this tag becomes text of the parent element
+    /// ");
+    /// ```
+    pub fn render<'a>(&'a self, source: &'a str) -> RenderedError<'a> {
+        RenderedError {
+            error: self,
+            source,
+            name: None,
+            color: false,
+        }
+    }
+}
+
+/// A [`Display`] adapter that renders an [`NxmlError`] with source snippets,
+/// created by [`NxmlError::render`].
+#[derive(Debug, Clone, Copy)]
+pub struct RenderedError<'a> {
+    error: &'a NxmlError,
+    source: &'a str,
+    name: Option<&'a str>,
+    color: bool,
+}
+
+impl<'a> RenderedError<'a> {
+    /// Set the name of the source (e.g. the file path) to show next to the
+    /// line and column.
+    pub fn name(mut self, name: &'a str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// Use ANSI escape codes to color the output.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+
+    fn paint(&self, style: &'static str) -> &'static str {
+        if self.color {
+            style
+        } else {
+            ""
+        }
+    }
+}
+
+/// An underlined part of a single line.
+#[derive(Debug)]
+struct Mark {
+    line: usize,
+    start: usize,
+    end: usize,
+    primary: bool,
+    label: &'static str,
+}
+
+impl Display for RenderedError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (blue, bold, reset) = (self.paint(BLUE), self.paint(BOLD), self.paint(RESET));
+        let err = &self.error.err;
+        let (red, severity) = match err.severity() {
+            Severity::Error => (self.paint(RED), "error"),
+            Severity::Warning => (self.paint(YELLOW), "warning"),
+        };
+        let lines = self.source.split('\n').collect::<Vec<_>>();
+
+        let mut marks = Vec::new();
+        if let Some(open_tag) = &self.error.open_tag {
+            self.mark(&mut marks, &lines, open_tag, false, "opening tag here");
+        }
+        self.mark(&mut marks, &lines, &self.error.span, true, "");
+        marks.sort_by_key(|m| (m.line, !m.primary));
+
+        let start = floor_char_boundary(self.source, self.error.span.start);
+        let at = Position::at(self.source, start);
+        let gutter = marks.last().map_or(1, |m| (m.line + 1).to_string().len());
+
+        let code = err.code();
+        writeln!(f, "{red}{severity}[{code}]{reset}{bold}: {err}{reset}")?;
+        write!(f, "{:gutter$}{blue}-->{reset} ", "")?;
+        if let Some(name) = self.name {
+            write!(f, "{name}:")?;
+        }
+        writeln!(f, "{at}")?;
+        writeln!(f, "{:gutter$} {blue}|{reset}", "")?;
+
+        let mut previous = None;
+        for (i, mark) in marks.iter().enumerate() {
+            if previous != Some(mark.line) {
+                if previous.is_some_and(|p| p + 1 < mark.line) {
+                    writeln!(f, "{blue}...{reset}")?;
+                }
+                let line = expand_tabs(lines[mark.line]);
+                let number = mark.line + 1;
+                writeln!(f, "{blue}{number:>gutter$} |{reset} {}", line.trim_end())?;
+                previous = Some(mark.line);
+            }
+            let (style, symbol) = if mark.primary {
+                (red, "^")
+            } else {
+                (blue, "-")
+            };
+            let underline = symbol.repeat((mark.end - mark.start).max(1));
+            // only label the last line of a multiline mark
+            let last = marks[i + 1..]
+                .iter()
+                .all(|m| m.primary != mark.primary || m.label != mark.label);
+            let label = if last && !mark.label.is_empty() {
+                format!(" {}", mark.label)
+            } else {
+                String::new()
+            };
+            writeln!(
+                f,
+                "{:gutter$} {blue}|{reset} {:start$}{style}{underline}{label}{reset}",
+                "",
+                "",
+                start = mark.start,
+            )?;
+        }
+        writeln!(
+            f,
+            "{:gutter$} {blue}={reset} {bold}help{reset}: {}",
+            "",
+            err.help()
+        )
+    }
+}
+
+impl RenderedError<'_> {
+    fn mark(
+        &self,
+        marks: &mut Vec<Mark>,
+        lines: &[&str],
+        span: &Range<usize>,
+        primary: bool,
+        label: &'static str,
+    ) {
+        let start = floor_char_boundary(self.source, span.start);
+        let end = floor_char_boundary(self.source, span.end).max(start);
+        let text = &self.source[start..end];
+
+        let first = self.source[..start].matches('\n').count();
+        let mut column = start - self.source[..start].rfind('\n').map_or(0, |i| i + 1);
+
+        for (i, part) in text.trim_end().split('\n').enumerate() {
+            let line = first + i;
+            let Some(content) = lines.get(line) else {
+                break;
+            };
+            // the underline starts at the first non-whitespace character on
+            // the continuation lines
+            if i > 0 {
+                column = content.len() - content.trim_start().len();
+            }
+            let from = column.min(content.len());
+            let to = (column + part.len()).min(content.len()).max(from);
+            marks.push(Mark {
+                line,
+                start: visual_width(&content[..from]),
+                end: visual_width(&content[..to]),
+                primary,
+                label,
+            });
+        }
+    }
+}
+
+fn floor_char_boundary(s: &str, mut index: usize) -> usize {
+    index = index.min(s.len());
+    while !s.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn visual_width(s: &str) -> usize {
+    s.chars()
+        .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+        .sum()
+}
+
+fn expand_tabs(s: &str) -> String {
+    s.replace('\t', &" ".repeat(TAB_WIDTH))
+}
+
+#[cfg(feature = "miette")]
+impl miette::Diagnostic for NxmlError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new(self.err.code()))
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        Some(match self.err.severity() {
+            Severity::Error => miette::Severity::Error,
+            Severity::Warning => miette::Severity::Warning,
+        })
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        Some(Box::new(self.err.help()))
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = miette::LabeledSpan> + '_>> {
+        let primary = miette::LabeledSpan::new_primary_with_span(None, self.span.clone());
+        let open_tag = self
+            .open_tag
+            .clone()
+            .map(|span| miette::LabeledSpan::new_with_span(Some("opening tag here".into()), span));
+        Some(Box::new(std::iter::once(primary).chain(open_tag)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn renders_multiline_tags_and_tabs() {
+        let source = "<Entity>\n\t<Base\n\t\tfile=\"a.xml\"\n\t>\n\t<Oops/>\n</Entity>";
+
+        let err = parse(source).unwrap_err();
+        assert!(matches!(err.err, NxmlErr::MismatchedClosingTag { .. }));
+
+        assert_eq!(
+            err.render(source).to_string(),
+            "\
+error[NXML0003]: Closing element is in wrong order. Expected '</Base>', but instead got 'Entity'
+ --> 6:1
+  |
+2 |     <Base
+  |     -----
+3 |         file=\"a.xml\"
+  |         ------------
+4 |     >
+  |     - opening tag here
+...
+6 | </Entity>
+  | ^^^^^^^^
+  = help: Noita closes the current element anyway, no matter the name, and the '>' of this tag becomes text of the parent element
+"
+        );
+    }
+
+    #[test]
+    fn renders_warnings_in_color() {
+        let source = "<Entity/>\n<Entity/>";
+
+        let parsed = ParseOptions::new().warnings().parse(source).unwrap();
+
+        assert_eq!(
+            parsed.warnings[0].render(source).color(true).to_string(),
+            "\
+\x1b[1;33mwarning[NXML0013]\x1b[0m\x1b[1m: Content after the root element is ignored\x1b[0m
+ \x1b[1;34m-->\x1b[0m 2:1
+  \x1b[1;34m|\x1b[0m
+\x1b[1;34m2 |\x1b[0m <Entity/>
+  \x1b[1;34m|\x1b[0m \x1b[1;33m^^^^^^^^^\x1b[0m
+  \x1b[1;34m=\x1b[0m \x1b[1mhelp\x1b[0m: Noita stops reading after the root element is closed, while a \
+standard XML parser rejects the file
+"
+        );
+    }
+}
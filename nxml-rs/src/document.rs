@@ -0,0 +1,195 @@
+use std::{borrow::Cow, io::Read, rc::Rc, sync::Arc};
+
+use crate::{
+    decode::{read_to_string, Detected, Encoding, ReadError},
+    element::ElementRef,
+    parser::{parse, parse_lenient, NxmlError},
+};
+
+/// An owner of the source text that a [`Document`] can borrow from.
+///
+/// # Safety
+/// The string returned by [`as_str`](Self::as_str) must stay at the same
+/// address and unchanged for as long as the value is alive, even when the
+/// value itself is moved - i.e. it must be on the heap (or static), and not
+/// inline in the value.
+///
+/// Moving the value must also not assert unique access to the string, as
+/// the document keeps borrowing it across the moves. This rules out a
+/// [`Box`] (and anything that holds one by value), which the compiler is
+/// allowed to treat like a `&mut`, so a `Box<str>` should be converted into
+/// a [`String`] first.
+///
+/// For example, a wrapper around a read-only memory map of a file that is
+/// not modified while it is mapped can implement this trait.
+pub unsafe trait Source {
+    fn as_str(&self) -> &str;
+}
+
+unsafe impl Source for String {
+    fn as_str(&self) -> &str {
+        self
+    }
+}
+
+unsafe impl Source for Arc<str> {
+    fn as_str(&self) -> &str {
+        self
+    }
+}
+
+unsafe impl Source for Rc<str> {
+    fn as_str(&self) -> &str {
+        self
+    }
+}
+
+unsafe impl Source for &'static str {
+    fn as_str(&self) -> &str {
+        self
+    }
+}
+
+unsafe impl Source for Cow<'static, str> {
+    fn as_str(&self) -> &str {
+        self
+    }
+}
+
+/// A parsed element bundled together with the source text it borrows from.
+///
+/// Unlike [`ElementRef`], it has no lifetime, so it can be stored in caches
+/// or sent to other threads, and unlike
+/// [`Element`](crate::Element), it does not copy every string out of the
+/// source.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// # use std::sync::Arc;
+/// let source: Arc<str> = r#"<Entity><LuaComponent script_source_file="a.lua"/></Entity>"#.into();
+///
+/// let document = Document::parse(source).unwrap();
+///
+/// let handle = std::thread::spawn(move || {
+///     (document.root() / "LuaComponent" % "script_source_file").to_owned()
+/// });
+///
+/// assert_eq!(handle.join().unwrap(), "a.lua");
+/// ```
+#[derive(Debug)]
+pub struct Document<S: Source = String> {
+    // declared first so that it is dropped before the source
+    root: ElementRef<'static>,
+    source: S,
+}
+
+impl<S: Source> Document<S> {
+    /// Parse the source, see [`parse`].
+    pub fn parse(source: S) -> Result<Self, NxmlError> {
+        // SAFETY: the source is never touched mutably and it is stored
+        // together with the element, so the string is alive and stays at the
+        // same address (as guaranteed by the `Source` impl) for as long as
+        // the element is.
+        // The element is only ever handed out with the lifetime of a borrow
+        // of the document.
+        let text: &'static str = unsafe { &*(source.as_str() as *const str) };
+        let root = parse(text)?;
+        Ok(Self { root, source })
+    }
+
+    /// Lenient version of [`Document::parse`], see [`parse_lenient`].
+    pub fn parse_lenient(source: S) -> (Self, Vec<NxmlError>) {
+        // SAFETY: see `Document::parse`
+        let text: &'static str = unsafe { &*(source.as_str() as *const str) };
+        let (root, errors) = parse_lenient(text);
+        (Self { root, source }, errors)
+    }
+
+    /// Parse the source with the given options, returning the recovered
+    /// errors and the warnings along with the document.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn parse_with(
+        source: S,
+        options: &crate::options::ParseOptions,
+    ) -> Result<(Self, Vec<NxmlError>, Vec<NxmlError>), NxmlError> {
+        // SAFETY: see `Document::parse`
+        let text: &'static str = unsafe { &*(source.as_str() as *const str) };
+        let parsed = options.parse(text)?;
+        let document = Self {
+            root: parsed.root,
+            source,
+        };
+        Ok((document, parsed.errors, parsed.warnings))
+    }
+
+    /// The parsed root element.
+    pub fn root(&self) -> &ElementRef<'_> {
+        // SAFETY: see `Document::parse` - the element only borrows from the
+        // source, so shortening its lifetime is fine, but the compiler cannot
+        // see that as `GenericElement` is invariant over its storage.
+        unsafe { &*(&self.root as *const ElementRef<'static> as *const ElementRef<'_>) }
+    }
+
+    /// The source text.
+    pub fn source(&self) -> &str {
+        self.source.as_str()
+    }
+
+    /// Drop the parsed element and get the source back.
+    pub fn into_source(self) -> S {
+        let Self { root, source } = self;
+        drop(root);
+        source
+    }
+}
+
+impl Document {
+    /// Read everything from the reader and parse it, see
+    /// [`parse_reader`](crate::parse_reader).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let file: &[u8] = b"\xEF\xBB\xBF<Entity/>";
+    ///
+    /// let (document, detected) = Document::read(file, None).unwrap();
+    ///
+    /// assert_eq!(document.root().name, "Entity");
+    /// assert!(detected.bom);
+    /// ```
+    pub fn read(
+        reader: impl Read,
+        fallback: Option<Encoding>,
+    ) -> Result<(Self, Detected), ReadError> {
+        let (text, detected) = read_to_string(reader, fallback)?;
+        Ok((Self::parse(text)?, detected))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // these are mostly meant for Miri, which checks that moving the source
+    // around does not invalidate the borrows of the element
+
+    fn moved<S: Source>(source: S) {
+        let document = Document::parse(source).unwrap();
+        let documents = vec![document];
+        let document = documents.into_iter().next().unwrap();
+
+        assert_eq!(document.root() / "b" % "c", "d");
+        assert_eq!(document.into_source().as_str(), "<a><b c=\"d\"/></a>");
+    }
+
+    #[test]
+    fn every_source_survives_a_move() {
+        let source = "<a><b c=\"d\"/></a>";
+        moved(source.to_owned());
+        moved(Arc::<str>::from(source));
+        moved(Rc::<str>::from(source));
+        moved(source);
+        moved(Cow::Owned(source.to_owned()));
+    }
+}
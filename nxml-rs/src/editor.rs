@@ -0,0 +1,326 @@
+use std::{fmt::Display, ops::Range};
+
+use thiserror::Error;
+
+use crate::{
+    element::ElementRef,
+    parser::{parse_spanned, ElementSpans, NxmlError},
+    tokenizer::is_name,
+};
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum EditError {
+    #[error("No element at path {0:?}")]
+    NoSuchElement(Vec<usize>),
+    #[error("Edit at {new:?} overlaps with an earlier edit at {existing:?}")]
+    Overlapping {
+        existing: Range<usize>,
+        new: Range<usize>,
+    },
+    #[error("Attribute value {0:?} contains a '\"', which cannot be quoted")]
+    UnquotableValue(String),
+    #[error("Attribute name {0:?} would not be read back as a single name")]
+    InvalidName(String),
+}
+
+/// An editor that changes the source text in place, touching only the parts
+/// of it that are being edited, so that comments, whitespace and the layout
+/// of everything else stay exactly as they were.
+///
+/// Elements are addressed by paths of child indices, e.g. `&[1, 0]` is the
+/// first child of the second child of the root, and `&[]` is the root
+/// itself. The indices are the ones of the original source (as seen in
+/// [`root`](Self::root)), the edits do not shift them.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let source = r#"<Entity>
+///     <!-- runs the thing -->
+///     <LuaComponent
+///         script_source_file="mods/blah/thing.lua"
+///         execute_every_n_frame="-1" >
+///     </LuaComponent>
+///     <TestComponent debug="1" blah="blah" />
+/// </Entity>
+/// "#;
+///
+/// let mut editor = Editor::new(source).unwrap();
+///
+/// editor.set_attr(&[0], "execute_every_n_frame", "60").unwrap();
+/// editor.remove_attr(&[1], "debug").unwrap();
+/// editor.insert_child(&[], 1, nxml!(<ElectricityComponent energy="0" />)).unwrap();
+///
+/// assert_eq!(editor.finish(), r#"<Entity>
+///     <!-- runs the thing -->
+///     <LuaComponent
+///         script_source_file="mods/blah/thing.lua"
+///         execute_every_n_frame="60" >
+///     </LuaComponent>
+///     <ElectricityComponent energy="0"/>
+///     <TestComponent blah="blah" />
+/// </Entity>
+/// "#);
+/// ```
+#[derive(Debug)]
+pub struct Editor<'s> {
+    source: &'s str,
+    root: ElementRef<'s>,
+    spans: ElementSpans,
+    edits: Vec<(Range<usize>, String)>,
+}
+
+impl<'s> Editor<'s> {
+    /// Parse the source and create an editor for it.
+    pub fn new(source: &'s str) -> Result<Self, NxmlError> {
+        let (root, spans) = parse_spanned(source)?;
+        Ok(Self {
+            source,
+            root,
+            spans,
+            edits: Vec::new(),
+        })
+    }
+
+    /// The original parsed element, useful for finding the paths to edit.
+    pub fn root(&self) -> &ElementRef<'s> {
+        &self.root
+    }
+
+    /// Set the value of an attribute, adding it after the last attribute if
+    /// it is not there.
+    ///
+    /// If the attribute is repeated, every occurrence is changed.
+    ///
+    /// The values are always written in double quotes, and Noita has no way
+    /// to escape them, so a value with a `"` in it is an error, as is a key
+    /// that is empty or has whitespace, `<`, `>`, `=` or `/` in it.
+    pub fn set_attr(&mut self, path: &[usize], key: &str, value: &str) -> Result<(), EditError> {
+        if !is_name(key) {
+            return Err(EditError::InvalidName(key.to_owned()));
+        }
+        if value.contains('"') {
+            return Err(EditError::UnquotableValue(value.to_owned()));
+        }
+        let spans = self.spans(path)?;
+        let mut edits = Vec::new();
+        for attribute in &spans.attributes {
+            if &self.source[attribute.key.range()] == key {
+                edits.push((attribute.value.range(), format!("\"{value}\"")));
+            }
+        }
+        if edits.is_empty() {
+            let end = spans
+                .attributes
+                .last()
+                .map_or(spans.name.end, |a| a.value.end);
+            edits.push((end..end, format!(" {key}=\"{value}\"")));
+        }
+        self.add(edits)
+    }
+
+    /// Remove an attribute, along with the whitespace before it.
+    ///
+    /// If the attribute is repeated, every occurrence is removed. Removing
+    /// an attribute that is not there is not an error.
+    pub fn remove_attr(&mut self, path: &[usize], key: &str) -> Result<(), EditError> {
+        let spans = self.spans(path)?;
+        let edits = spans
+            .attributes
+            .iter()
+            .filter(|a| &self.source[a.key.range()] == key)
+            .map(|a| (self.trim_start(a.key.start)..a.value.end, String::new()))
+            .collect();
+        self.add(edits)
+    }
+
+    /// Insert a child element at the given index among the children of the
+    /// element at the path, indenting it the same way as its siblings are.
+    ///
+    /// The child is written with its [`Display`] impl, so pass e.g.
+    /// `element.display()` to have it pretty-printed.
+    pub fn insert_child(
+        &mut self,
+        path: &[usize],
+        index: usize,
+        child: impl Display,
+    ) -> Result<(), EditError> {
+        let spans = self.spans(path)?;
+        let indent = self.indent(spans.open_tag.start);
+        let child_indent = match spans.children.first() {
+            Some(sibling) => self.indent(sibling.open_tag.start),
+            None => format!("{indent}    "),
+        };
+        let child = child
+            .to_string()
+            .replace('\n', &format!("\n{child_indent}"));
+
+        let edit = if let Some(sibling) = spans.children.get(index) {
+            // before the whitespace in front of the sibling, which is where
+            // removing the sibling starts, so that the two can be combined
+            let at = self.trim_start(sibling.open_tag.start);
+            (at..at, format!("\n{child_indent}{child}"))
+        } else if let Some(sibling) = spans.children.last() {
+            let at = element_end(sibling);
+            (at..at, format!("\n{child_indent}{child}"))
+        } else if let Some(close_tag) = spans.close_tag {
+            let at = self.trim_start(close_tag.start);
+            (at..at, format!("\n{child_indent}{child}"))
+        } else if self.source[spans.open_tag.range()].ends_with("/>") {
+            let end = spans.open_tag.end;
+            let name = &self.source[spans.name.range()];
+            (
+                end - 2..end,
+                format!(">\n{child_indent}{child}\n{indent}</{name}>"),
+            )
+        } else {
+            let at = element_end(spans);
+            (at..at, format!("\n{child_indent}{child}"))
+        };
+        self.add(vec![edit])
+    }
+
+    /// Remove the element at the path, along with the whitespace before it.
+    pub fn remove(&mut self, path: &[usize]) -> Result<(), EditError> {
+        let spans = self.spans(path)?;
+        let range = self.trim_start(spans.open_tag.start)..element_end(spans);
+        self.add(vec![(range, String::new())])
+    }
+
+    /// Apply all the edits and return the new source text.
+    pub fn finish(mut self) -> String {
+        // insertions go before the replacements that start at the same offset
+        self.edits
+            .sort_by_key(|(range, _)| (range.start, range.end));
+        let mut result = String::with_capacity(self.source.len());
+        let mut position = 0;
+        for (range, replacement) in &self.edits {
+            result.push_str(&self.source[position..range.start]);
+            result.push_str(replacement);
+            position = range.end;
+        }
+        result.push_str(&self.source[position..]);
+        result
+    }
+
+    fn spans(&self, path: &[usize]) -> Result<&ElementSpans, EditError> {
+        path.iter()
+            .try_fold(&self.spans, |spans, &i| spans.children.get(i))
+            .ok_or_else(|| EditError::NoSuchElement(path.to_vec()))
+    }
+
+    fn add(&mut self, edits: Vec<(Range<usize>, String)>) -> Result<(), EditError> {
+        for (new, _) in &edits {
+            for (existing, _) in &self.edits {
+                // (this also catches insertions strictly inside of replaced ranges)
+                let overlaps = new.start < existing.end && existing.start < new.end;
+                if overlaps {
+                    return Err(EditError::Overlapping {
+                        existing: existing.clone(),
+                        new: new.clone(),
+                    });
+                }
+            }
+        }
+        self.edits.extend(edits);
+        Ok(())
+    }
+
+    /// Moves the offset back over any whitespace before it.
+    fn trim_start(&self, offset: usize) -> usize {
+        self.source[..offset].trim_end().len()
+    }
+
+    /// The whitespace at the start of the line the offset is on.
+    fn indent(&self, offset: usize) -> String {
+        let line_start = self.source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let line = &self.source[line_start..offset];
+        line[..line.len() - line.trim_start().len()].to_owned()
+    }
+}
+
+/// The end of the whole element, including its content.
+fn element_end(spans: &ElementSpans) -> usize {
+    if let Some(close_tag) = spans.close_tag {
+        return close_tag.end;
+    }
+    let children = spans.children.last().map(element_end);
+    let text = spans.text.last().map(|s| s.end);
+    spans
+        .open_tag
+        .end
+        .max(children.unwrap_or(0))
+        .max(text.unwrap_or(0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_unwritable_attributes() {
+        let mut editor = Editor::new("<Entity a=\"1\"/>").unwrap();
+
+        let err = editor.set_attr(&[], "a", "say \"hi\"").unwrap_err();
+        assert_eq!(err, EditError::UnquotableValue("say \"hi\"".into()));
+
+        for key in ["", "a b", "a=", "\"a\"", "a/>"] {
+            let err = editor.set_attr(&[], key, "1").unwrap_err();
+            assert_eq!(err, EditError::InvalidName(key.into()));
+        }
+
+        editor.set_attr(&[], "a", "say 'hi'").unwrap();
+        assert_eq!(editor.finish(), "<Entity a=\"say 'hi'\"/>");
+    }
+
+    #[test]
+    fn expands_self_closing_elements() {
+        let source = "<Entity>\n  <Base file=\"a.xml\" />\n</Entity>";
+        let mut editor = Editor::new(source).unwrap();
+
+        editor.insert_child(&[0], 0, "<Child/>").unwrap();
+        editor.remove(&[5]).unwrap_err();
+
+        assert_eq!(
+            editor.finish(),
+            "<Entity>\n  <Base file=\"a.xml\" >\n      <Child/>\n  </Base>\n</Entity>"
+        );
+    }
+
+    #[test]
+    fn rejects_overlapping_edits() {
+        let source = "<Entity>\n  <Base file=\"a.xml\" />\n</Entity>";
+        let mut editor = Editor::new(source).unwrap();
+
+        editor.remove(&[0]).unwrap();
+
+        assert!(matches!(
+            editor.set_attr(&[0], "file", "b.xml"),
+            Err(EditError::Overlapping { .. })
+        ));
+        assert_eq!(editor.finish(), "<Entity>\n</Entity>");
+    }
+
+    #[test]
+    fn inserts_before_a_replacement_at_the_same_offset() {
+        let mut editor = Editor::new("<a><b/></a>").unwrap();
+
+        editor.remove(&[0]).unwrap();
+        editor.insert_child(&[], 0, "<c/>").unwrap();
+
+        assert_eq!(editor.finish(), "<a>\n<c/></a>");
+    }
+
+    #[test]
+    fn replaces_indented_elements() {
+        let source = "<a>\n    <b/>\n    <c/>\n</a>";
+        let mut editor = Editor::new(source).unwrap();
+
+        editor.remove(&[0]).unwrap();
+        editor.insert_child(&[], 0, "<x/>").unwrap();
+        editor.remove(&[1]).unwrap();
+        editor.insert_child(&[], 1, "<y/>").unwrap();
+
+        assert_eq!(editor.finish(), "<a>\n    <x/>\n    <y/>\n</a>");
+    }
+}
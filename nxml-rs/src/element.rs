@@ -1,42 +1,177 @@
 use std::{
-    borrow::Cow,
-    fmt::{self, Display},
+    borrow::{Borrow, Cow},
+    fmt::{self, Debug, Display},
+    hash::Hash,
     ops::{Div, Rem},
 };
 
 #[cfg(feature = "compact_str")]
 use compact_str::{CompactString, ToCompactString};
 
-#[cfg(feature = "indexmap")]
-type Map<K, V> = indexmap::IndexMap<K, V>;
-#[cfg(not(feature = "indexmap"))]
-type Map<K, V> = std::collections::HashMap<K, V>;
+#[cfg(feature = "small_map")]
+pub(crate) type Map<K, V> = crate::map::SmallMap<K, V>;
+#[cfg(all(feature = "indexmap", not(feature = "small_map")))]
+pub(crate) type Map<K, V> = indexmap::IndexMap<K, V>;
+#[cfg(not(any(feature = "indexmap", feature = "small_map")))]
+pub(crate) type Map<K, V> = std::collections::HashMap<K, V>;
+
+/// Collect entries with no repeated keys into a [`Map`], without looking
+/// each key up first, which takes linear time with the `small_map` feature.
+pub(crate) fn unique_map<K: Eq + Hash, V>(
+    entries: impl ExactSizeIterator<Item = (K, V)>,
+) -> Map<K, V> {
+    #[cfg(feature = "small_map")]
+    return crate::map::SmallMap::from_unique(entries);
+    #[cfg(not(feature = "small_map"))]
+    entries.collect()
+}
 
-/// An XML element.
+/// The string storage of a [`GenericElement`], which decides the types of
+/// its names, attribute values and text content.
 ///
-/// This is a result of zero-copy parsing, meaning you might run into lifetime
-/// issues.
+/// It is implemented for `&str` ([`ElementRef`]), for `String` and, with
+/// the `compact_str` feature, for `CompactString` (either one being
+/// [`Element`]).
 ///
-/// If you need to own the element separately from the source XML, you can
-/// convert it to [`Element`] using
-/// [`into_owned`](struct.ElementRef.html#method.into_owned).
+/// With the `intern` feature, the names of the owned storages are interned
+/// [`Name`](crate::Name)s.
+pub trait NxmlStr: Sized {
+    /// The type of the element and attribute names.
+    type Name: AsRef<str> + Borrow<str> + Debug + Clone + Eq + Hash;
+    /// The type of the attribute values.
+    type Value: AsRef<str> + Debug + Clone + Eq;
+    /// The type of the text content.
+    type Text: AsRef<str> + Debug + Clone + Eq;
+
+    /// The text content of a new element.
+    fn empty_text() -> Self::Text;
+}
+
+/// Something that can be converted into the strings of a
+/// [`GenericElement<S>`], used by its constructor and setters.
+///
+/// The borrowed storage takes anything that can be borrowed as a `&str`, and
+/// the owned ones take anything that can be displayed, e.g. numbers.
+pub trait IntoNxmlStr<S: NxmlStr> {
+    /// Convert into an element or attribute name.
+    fn into_name(self) -> S::Name;
+    /// Convert into an attribute value.
+    fn into_value(self) -> S::Value;
+    /// Convert into a text content.
+    fn into_text(self) -> S::Text;
+}
+
+impl<'s> NxmlStr for &'s str {
+    type Name = &'s str;
+    type Value = &'s str;
+    type Text = Cow<'s, str>;
+
+    fn empty_text() -> Self::Text {
+        Cow::Borrowed("")
+    }
+}
+
+impl<'s, T: AsRef<str> + ?Sized> IntoNxmlStr<&'s str> for &'s T {
+    fn into_name(self) -> &'s str {
+        self.as_ref()
+    }
+
+    fn into_value(self) -> &'s str {
+        self.as_ref()
+    }
+
+    fn into_text(self) -> Cow<'s, str> {
+        Cow::Borrowed(self.as_ref())
+    }
+}
+
+impl NxmlStr for String {
+    #[cfg(feature = "intern")]
+    type Name = crate::intern::Name;
+    #[cfg(not(feature = "intern"))]
+    type Name = String;
+    type Value = String;
+    type Text = String;
+
+    fn empty_text() -> Self::Text {
+        String::new()
+    }
+}
+
+impl<T: ToString> IntoNxmlStr<String> for T {
+    // interns the name with the `intern` feature
+    #[cfg_attr(not(feature = "intern"), allow(clippy::useless_conversion))]
+    fn into_name(self) -> <String as NxmlStr>::Name {
+        self.to_string().into()
+    }
+
+    fn into_value(self) -> String {
+        self.to_string()
+    }
+
+    fn into_text(self) -> String {
+        self.to_string()
+    }
+}
+
+#[cfg(feature = "compact_str")]
+impl NxmlStr for CompactString {
+    #[cfg(feature = "intern")]
+    type Name = crate::intern::Name;
+    #[cfg(not(feature = "intern"))]
+    type Name = CompactString;
+    type Value = CompactString;
+    type Text = CompactString;
+
+    fn empty_text() -> Self::Text {
+        CompactString::const_new("")
+    }
+}
+
+#[cfg(feature = "compact_str")]
+impl<T: ToCompactString> IntoNxmlStr<CompactString> for T {
+    #[cfg_attr(not(feature = "intern"), allow(clippy::useless_conversion))]
+    fn into_name(self) -> <CompactString as NxmlStr>::Name {
+        self.to_compact_string().into()
+    }
+
+    fn into_value(self) -> CompactString {
+        self.to_compact_string()
+    }
+
+    fn into_text(self) -> CompactString {
+        self.to_compact_string()
+    }
+}
+
+#[cfg(feature = "compact_str")]
+type Str = CompactString;
+#[cfg(not(feature = "compact_str"))]
+type Str = String;
+
+/// An XML element, generic over the [storage](NxmlStr) of its strings.
+///
+/// This is rarely used directly, instead it is either an [`ElementRef`] or
+/// an [`Element`], but the methods that are not about constructing the
+/// element are written once, for any storage.
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct ElementRef<'s> {
+pub struct GenericElement<S: NxmlStr> {
     /// The name of the element, e.g. `LuaComponent` in `<LuaComponent />`.
-    pub name: &'s str,
+    pub name: S::Name,
     /// The text content of the element, e.g. `hello` in
     /// `<SomeComponent>hello</SomeComponent>`.
     ///
     /// If there are multiple text nodes, they are concatenated into a single
-    /// string with spaces between them. This is the only case where the
-    /// parsing is not zero-copy, as the text is discontinuous in the source
-    /// XML.
+    /// string with spaces between them. For an [`ElementRef`], this is the
+    /// only case where the parsing is not zero-copy, as the text is
+    /// discontinuous in the source XML.
     ///
-    /// If there is no text content, the value is `Cow::Borrowed("")`.
-    pub text_content: Cow<'s, str>,
+    /// If there is no text content, the value is empty, which for an
+    /// [`ElementRef`] is `Cow::Borrowed("")`.
+    pub text_content: S::Text,
     /// A map of element attributes, e.g. `name="comp"` in `<SomeComponent
     /// name="comp" />`, where the key is `name` and the value is `comp`.
-    pub attributes: Map<&'s str, &'s str>,
+    pub attributes: Map<S::Name, S::Value>,
     /// A list of child elements, e.g. [`<SomeComponent/>`,
     /// `<SomeOtherComponent/>`] in
     /// ```xml
@@ -45,110 +180,200 @@ pub struct ElementRef<'s> {
     ///     <SomeOtherComponent/>
     /// </Entity>
     /// ```
-    pub children: Vec<ElementRef<'s>>,
+    pub children: Vec<GenericElement<S>>,
 }
 
-impl<'s> ElementRef<'s> {
+/// An XML element.
+///
+/// This is a result of zero-copy parsing, meaning you might run into lifetime
+/// issues.
+///
+/// If you need to own the element separately from the source XML, you can
+/// convert it to [`Element`] using
+/// [`to_owned`](GenericElement::to_owned).
+///
+/// The lifetime is invariant, as the storage goes through the associated
+/// types of [`NxmlStr`], so an `ElementRef<'long>` does not coerce to an
+/// `ElementRef<'short>`.
+pub type ElementRef<'s> = GenericElement<&'s str>;
+
+/// An owned XML element. Slightly easier to work with than [`ElementRef`].
+///
+/// With the `compact_str` feature, the strings are `CompactString`s, and
+/// with the `intern` feature the names are interned [`Name`](crate::Name)s.
+pub type Element = GenericElement<Str>;
+
+impl<S: NxmlStr> GenericElement<S> {
     /// Create a new element with the given name.
-    pub fn new(name: &'s str) -> Self {
+    pub fn new(name: impl IntoNxmlStr<S>) -> Self {
         Self {
-            name,
-            attributes: Map::new(),
+            name: name.into_name(),
+            attributes: Map::default(),
             children: Vec::new(),
-            text_content: Cow::Borrowed(""),
+            text_content: S::empty_text(),
         }
     }
 
-    /// Convert this element to an owned [`Element`] by cloning all the strings.
+    /// A shorthand for getting an attribute value.
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = nxml!(<Entity key="value"/>);
+    ///
+    /// assert_eq!(element.attr("key"), Some("value"));
+    /// ```
+    pub fn attr(&self, key: &str) -> Option<&str> {
+        self.attributes.get(key).map(|s| s.as_ref())
+    }
+
+    /// Find the first child element with the given name.
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = nxml_ref!(<Entity><Child>"hello"</Child></Entity>);
     ///
+    /// assert_eq!(element.child("Child").unwrap().text_content, "hello");
+    /// ```
+    pub fn child(&self, name: &str) -> Option<&Self> {
+        self.children.iter().find(|c| c.name.as_ref() == name)
+    }
+
+    /// Find the first child element with the given name, mutable version.
     /// # Example
     /// ```rust
     /// # use nxml_rs::*;
-    /// # fn assert_static<T: 'static>(_: T) {}
-    /// let nonstatic_prop = String::from("value");
-    /// let element = nxml_ref!(<Entity {&nonstatic_prop} />);
+    /// let mut element = nxml!(<Entity><Child/></Entity>);
     ///
-    /// let owned_element = element.to_owned();
+    /// element.child_mut("Child").unwrap().text_content = "world".into();
     ///
-    /// assert_static(owned_element);
+    /// assert_eq!(element.child("Child").unwrap().text_content, "world");
+    pub fn child_mut(&mut self, name: &str) -> Option<&mut Self> {
+        self.children.iter_mut().find(|c| c.name.as_ref() == name)
+    }
+
+    /// Iterate over all child elements with the given name.
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = nxml_ref!(<Entity><Child/><Other/><Child/></Entity>);
+    ///
+    /// assert_eq!(element.children("Child").count(), 2);
     /// ```
-    pub fn to_owned(&self) -> Element {
-        #[cfg(feature = "compact_str")]
-        return Element {
-            name: self.name.to_compact_string(),
-            attributes: self
-                .attributes
-                .iter()
-                .map(|(&k, &v)| (k.to_compact_string(), v.to_compact_string()))
-                .collect(),
-            children: self.children.iter().map(|c| c.to_owned()).collect(),
-            text_content: self.text_content.to_compact_string(),
-        };
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Self> + 'a {
+        self.children
+            .iter()
+            .filter(move |c| c.name.as_ref() == name)
+    }
 
-        #[cfg(not(feature = "compact_str"))]
-        Element {
-            name: self.name.to_owned(),
-            attributes: self
-                .attributes
-                .iter()
-                .map(|(&k, &v)| (k.to_owned(), v.to_owned()))
-                .collect(),
-            children: self.children.iter().map(|c| c.to_owned()).collect(),
-            text_content: self.text_content.clone().into_owned(),
-        }
+    /// Iterate over all child elements with the given name, mutable version.
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let mut element = nxml!(<Entity><Child/><Other/><Child/></Entity>);
+    ///
+    /// for child in element.children_mut("Child") {
+    ///    child.text_content = "text".into();
+    /// }
+    ///
+    /// assert_eq!(element.to_string(), "<Entity><Child>text</Child><Other/><Child>text</Child></Entity>");
+    /// ```
+    pub fn children_mut<'a>(
+        &'a mut self,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a mut Self> + 'a {
+        self.children
+            .iter_mut()
+            .filter(move |c| c.name.as_ref() == name)
     }
-}
 
-#[cfg(feature = "compact_str")]
-type Str = compact_str::CompactString;
-#[cfg(not(feature = "compact_str"))]
-type Str = String;
+    /// A shorthand for setting an attribute value.
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let mut element = nxml!(<Entity />);
+    ///
+    /// element.set_attr("key", "value");
+    ///
+    /// assert_eq!(element.to_string(), "<Entity key=\"value\"/>");
+    pub fn set_attr(&mut self, key: impl IntoNxmlStr<S>, value: impl IntoNxmlStr<S>) {
+        self.attributes.insert(key.into_name(), value.into_value());
+    }
 
-/// An owned XML element. Slightly easier to work with than [`ElementRef`].
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Element {
-    /// The name of the element, e.g. `LuaComponent` in `<LuaComponent />`.
-    pub name: Str,
-    /// The text content of the element, e.g. `hello` in
-    /// `<SomeComponent>hello</SomeComponent>`.
+    /// A shorthand for removing an attribute value.
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let mut element = nxml_ref!(<Entity key="value" other="other" />);
     ///
-    /// If there are multiple text nodes, they are concatenated into a single
-    /// string with spaces between them.
-    pub text_content: Str,
-    /// A map of element attributes, e.g. `name="comp"` in `<SomeComponent
-    /// name="comp" />`, where the key is `name` and the value is `comp`.
-    pub attributes: Map<Str, Str>,
-    /// A list of child elements, e.g. [`<SomeComponent/>`,
-    /// `<SomeOtherComponent/>`] in
-    /// ```xml
-    /// <Entity>
-    ///     <SomeComponent/>
-    ///     <SomeOtherComponent/>
-    /// </Entity>
+    /// element.remove_attr("key");
+    ///
+    /// assert_eq!(element.to_string(), "<Entity other=\"other\"/>");
+    pub fn remove_attr(&mut self, key: &str) -> Option<S::Value> {
+        #[cfg(any(feature = "indexmap", feature = "small_map"))]
+        return self.attributes.shift_remove(key);
+
+        #[cfg(not(any(feature = "indexmap", feature = "small_map")))]
+        return self.attributes.remove(key);
+    }
+
+    /// Chained version of [`set_attr`](#method.set_attr).
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = Element::new("Entity")
+    ///     .with_attr("key", "value")
+    ///     .with_attr("number", 42);
+    ///
+    /// assert_eq!(element.attr("key"), Some("value"));
+    /// assert_eq!(element.attr("number"), Some("42"));
     /// ```
-    pub children: Vec<Element>,
-}
+    pub fn with_attr(mut self, key: impl IntoNxmlStr<S>, value: impl IntoNxmlStr<S>) -> Self {
+        self.set_attr(key, value);
+        self
+    }
 
-impl Element {
-    /// Create a new element with the given name.
-    #[cfg(feature = "compact_str")]
-    pub fn new(name: impl ToCompactString) -> Element {
-        Element {
-            name: name.to_compact_string(),
-            attributes: Map::new(),
-            children: Vec::new(),
-            text_content: CompactString::const_new(""),
-        }
+    /// Chained shorthand for setting the text content.
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = ElementRef::new("Entity")
+    ///     .with_text("hello");
+    ///
+    /// assert_eq!(element.to_string(), "<Entity>hello</Entity>");
+    /// ```
+    pub fn with_text(mut self, text: impl IntoNxmlStr<S>) -> Self {
+        self.text_content = text.into_text();
+        self
     }
 
-    /// Create a new element with the given name.
-    #[cfg(not(feature = "compact_str"))]
-    pub fn new(name: impl ToString) -> Element {
-        Element {
-            name: name.to_string(),
-            attributes: Map::new(),
-            children: Vec::new(),
-            text_content: String::new(),
+    /// Chained shorthand for adding a child element.
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = Element::new("Entity")
+    ///      .with_child(Element::new("Child"));
+    ///
+    /// assert_eq!(element.to_string(), "<Entity><Child/></Entity>");
+    /// ```
+    pub fn with_child(mut self, element: Self) -> Self {
+        self.children.push(element);
+        self
+    }
+
+    /// A customizable [`Display`] impl that pretty-prints the element.
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = nxml!(<Entity><Child/></Entity>);
+    ///
+    /// assert_eq!(element.display().indent_width(0).to_string(), "<Entity>\n<Child/>\n</Entity>");
+    /// ```
+    pub fn display(&self) -> PrettyDisplay<'_, Self> {
+        PrettyDisplay {
+            element: self,
+            indent_width: 4,
+            line_separator: "\n",
+            autoclose: true,
         }
     }
 
@@ -162,16 +387,51 @@ impl Element {
     ///
     /// assert_eq!(element_ref.to_string(), "<root><thing/><thing/></root>");
     /// ```
-    pub fn as_ref(&self) -> ElementRef {
+    pub fn as_ref(&self) -> ElementRef<'_> {
         ElementRef {
-            name: &self.name,
+            name: self.name.as_ref(),
             attributes: self
                 .attributes
                 .iter()
-                .map(|(k, v)| (k.as_str(), v.as_str()))
+                .map(|(k, v)| (k.as_ref(), v.as_ref()))
                 .collect(),
             children: self.children.iter().map(|c| c.as_ref()).collect(),
-            text_content: Cow::Borrowed(&self.text_content),
+            text_content: Cow::Borrowed(self.text_content.as_ref()),
+        }
+    }
+
+    /// Convert this element to an owned [`Element`] by cloning all the strings.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// # fn assert_static<T: 'static>(_: T) {}
+    /// let nonstatic_prop = String::from("value");
+    /// let element = nxml_ref!(<Entity {&nonstatic_prop} />);
+    ///
+    /// let owned_element = element.to_owned();
+    ///
+    /// assert_static(owned_element);
+    /// ```
+    pub fn to_owned(&self) -> Element {
+        self.convert()
+    }
+
+    /// Convert this element to one with a different string storage, e.g.
+    /// `GenericElement<String>` when [`Element`] uses `CompactString`.
+    pub fn convert<T: NxmlStr>(&self) -> GenericElement<T>
+    where
+        for<'a> &'a str: IntoNxmlStr<T>,
+    {
+        GenericElement {
+            name: self.name.as_ref().into_name(),
+            attributes: self
+                .attributes
+                .iter()
+                .map(|(k, v)| (k.as_ref().into_name(), v.as_ref().into_value()))
+                .collect(),
+            children: self.children.iter().map(|c| c.convert()).collect(),
+            text_content: self.text_content.as_ref().into_text(),
         }
     }
 }
@@ -183,322 +443,84 @@ impl Element {
 #[derive(Debug)]
 pub struct Text;
 
-macro_rules! dsl_impl {
-    (
-        #[$macro:ident]
-        impl $tpe:ident$(<$src:lifetime>)? {
-            attr($attr_str:ty) -> $attr_str_owned:ty,
-            text($text_str:ty)$(.$text_transform:ident())?,
-        }
-    ) => {
-        impl$(<$src>)? $tpe$(<$src>)? {
-
-            /// A shorthand for getting an attribute value.
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let element = ", stringify!($macro),"!(<Entity key=\"value\"/>);")]
-            ///
-            /// assert_eq!(element.attr("key"), Some("value"));
-            /// ```
-            pub fn attr(&self, key: &str) -> Option<&str> {
-                self.attributes.get(key).map(|s| s.as_ref())
-            }
-
-            /// Find the first child element with the given name.
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let element = ", stringify!($macro),"!(<Entity><Child>\"hello\"</Child></Entity>);")]
-            ///
-            /// assert_eq!(element.child("Child").unwrap().text_content, "hello");
-            /// ```
-            pub fn child(&self, name: &str) -> Option<&Self> {
-                self.children.iter().find(|c| c.name == name)
-            }
-
-            /// Find the first child element with the given name, mutable version.
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let mut element = ", stringify!($macro),"!(<Entity><Child/></Entity>);")]
-            ///
-            /// element.child_mut("Child").unwrap().text_content = "world".into();
-            ///
-            /// assert_eq!(element.child("Child").unwrap().text_content, "world");
-            pub fn child_mut(&mut self, name: &str) -> Option<&mut Self> {
-                self.children.iter_mut().find(|c| c.name == name)
-            }
-
-            /// Iterate over all child elements with the given name.
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let element = ", stringify!($macro),"!(<Entity><Child/><Other/><Child/></Entity>);")]
-            ///
-            /// assert_eq!(element.children("Child").count(), 2);
-            /// ```
-            pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Self> + 'a {
-                self.children.iter().filter(move |c| c.name == name)
-            }
-
-            /// Iterate over all child elements with the given name, mutable version.
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let mut element = ", stringify!($macro),"!(<Entity><Child/><Other/><Child/></Entity>);")]
-            ///
-            /// for child in element.children_mut("Child") {
-            ///    child.text_content = "text".into();
-            /// }
-            ///
-            /// assert_eq!(element.to_string(), "<Entity><Child>text</Child><Other/><Child>text</Child></Entity>");
-            /// ```
-            pub fn children_mut<'a>(
-                &'a mut self,
-                name: &'a str,
-            ) -> impl Iterator<Item = &'a mut Self> + 'a {
-                self.children.iter_mut().filter(move |c| c.name == name)
-            }
-
-            /// A shorthand for setting an attribute value.
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let mut element = ", stringify!($macro),"!(<Entity />);")]
-            ///
-            /// element.set_attr("key", "value");
-            ///
-            /// assert_eq!(element.to_string(), "<Entity key=\"value\"/>");
-            pub fn set_attr(&mut self, key: $attr_str, value: $attr_str) {
-                self.attributes.insert(key$(.$text_transform())?, value$(.$text_transform())?);
-            }
-
-            /// A shorthand for removing an attribute value.
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let mut element = ", stringify!($macro),"!(<Entity key=\"value\" other=\"other\" />);")]
-            ///
-            /// element.remove_attr("key");
-            ///
-            /// assert_eq!(element.to_string(), "<Entity other=\"other\"/>");
-            pub fn remove_attr(&mut self, key: &str) -> Option<$attr_str_owned> {
-                #[cfg(feature = "indexmap")]
-                return self.attributes.shift_remove(key);
-
-                #[cfg(not(feature = "indexmap"))]
-                return self.attributes.remove(key);
-            }
-
-            /// Chained version of [`set_attr`](#method.set_attr).
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let element = ", stringify!($tpe), "::new(\"Entity\")")]
-            ///     .with_attr("key", "value");
-            ///
-            /// assert_eq!(element.to_string(), "<Entity key=\"value\"/>");
-            /// ```
-            pub fn with_attr(mut self, key: $attr_str, value: $attr_str) -> Self {
-                self.set_attr(key, value);
-                self
-            }
-
-            /// Chained shorthand for setting the text content.
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let element = ", stringify!($tpe), "::new(\"Entity\")")]
-            ///     .with_text("hello");
-            ///
-            /// assert_eq!(element.to_string(), "<Entity>hello</Entity>");
-            /// ```
-            pub fn with_text(mut self, text: $text_str) -> Self {
-                self.text_content = text$(.$text_transform())?;
-                self
-            }
-
-            /// Chained shorthand for adding a child element.
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let element = ", stringify!($tpe), "::new(\"Entity\")")]
-            #[doc = concat!("     .with_child(", stringify!($tpe), "::new(\"Child\"));")]
-            ///
-            /// assert_eq!(element.to_string(), "<Entity><Child/></Entity>");
-            /// ```
-            pub fn with_child(mut self, element: Self) -> Self {
-                self.children.push(element);
-                self
-            }
-
-            /// A customizable [`Display`] impl that pretty-prints the element.
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let element = ", stringify!($macro),"!(<Entity><Child/></Entity>);")]
-            ///
-            /// assert_eq!(element.display().indent_width(0).to_string(), "<Entity>\n<Child/>\n</Entity>");
-            /// ```
-            pub fn display(&self) -> PrettyDisplay<'_, Self> {
-                PrettyDisplay {
-                    element: self,
-                    indent_width: 4,
-                    line_separator: "\n",
-                    autoclose: true,
-                }
-            }
-        }
-
-        impl<$($src,)? 'e> Div<&str> for &'e $tpe$(<$src>)? {
-            type Output = Self;
-
-            /// A chainable child element accessor
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let element = ", stringify!($macro),"!(<Entity><Child><Grandchild>\"hello\"</Grandchild></Child></Entity>);")]
-            ///
-            /// assert_eq!(&element / "Child" / "Grandchild" % Text, "hello");
-            /// ```
-            fn div(self, rhs: &str) -> Self::Output {
-                match self.child(rhs) {
-                    Some(child) => child,
-                    None => panic!("child element '{rhs}' not found"),
-                }
-            }
-        }
-
-        impl<$($src,)? 'e> Div<&str> for &'e mut $tpe$(<$src>)? {
-            type Output = Self;
-
-            /// A mutable version of the child accessor.
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let mut element = ", stringify!($macro),"!(<Entity><Child><Grandchild>hello</Grandchild></Child></Entity>);")]
-            ///
-            /// (&mut element / "Child").children.clear();
-            ///
-            /// assert_eq!(element.to_string(), "<Entity><Child/></Entity>");
-            fn div(self, rhs: &str) -> Self::Output {
-                match self.child_mut(rhs) {
-                    Some(child) => child,
-                    None => panic!("child element '{rhs}' not found"),
-                }
-            }
-        }
+impl<S: NxmlStr> Div<&str> for &GenericElement<S> {
+    type Output = Self;
 
-        impl<$($src,)? 'e> Rem<&str> for &'e $tpe$(<$src>)? {
-            type Output = &'e str;
-
-            /// A shorthand for getting an attribute value.
-            /// Not index because  `&element / "child" % "key"` is better
-            /// than `&(&element / "child")["key"]`.
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let element = ", stringify!($macro),"!(<Entity key=\"value\"/>);")]
-            ///
-            /// assert_eq!(&element % "key", "value");
-            fn rem(self, rhs: &str) -> Self::Output {
-                match self.attr(rhs) {
-                    Some(attr) => attr,
-                    None => panic!("attribute '{rhs}' not found"),
-                }
-            }
+    /// A chainable child element accessor
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = nxml_ref!(<Entity><Child><Grandchild>"hello"</Grandchild></Child></Entity>);
+    ///
+    /// assert_eq!(&element / "Child" / "Grandchild" % Text, "hello");
+    /// ```
+    fn div(self, rhs: &str) -> Self::Output {
+        match self.child(rhs) {
+            Some(child) => child,
+            None => panic!("child element '{rhs}' not found"),
         }
+    }
+}
 
-        impl<$($src,)? 'e> Rem<Text> for &'e $tpe$(<$src>)? {
-            type Output = &'e str;
-
-            /// A shorthand for getting the text content.
-            /// # Example
-            /// ```rust
-            /// # use nxml_rs::*;
-            #[doc = concat!("let element = ", stringify!($macro),"!(<Entity>\"hello\"</Entity>);")]
-            ///
-            /// assert_eq!(&element % Text, "hello");
-            /// ```
-            fn rem(self, _: Text) -> Self::Output {
-                &self.text_content
-            }
-        }
+impl<S: NxmlStr> Div<&str> for &mut GenericElement<S> {
+    type Output = Self;
 
-        impl<$($src)?> Display for $tpe$(<$src>)? {
-            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-                self.display().compact().fmt(f)
-            }
+    /// A mutable version of the child accessor.
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let mut element = nxml!(<Entity><Child><Grandchild>hello</Grandchild></Child></Entity>);
+    ///
+    /// (&mut element / "Child").children.clear();
+    ///
+    /// assert_eq!(element.to_string(), "<Entity><Child/></Entity>");
+    fn div(self, rhs: &str) -> Self::Output {
+        match self.child_mut(rhs) {
+            Some(child) => child,
+            None => panic!("child element '{rhs}' not found"),
         }
-    };
-}
-
-dsl_impl! {
-    #[nxml_ref]
-    impl ElementRef<'s> {
-        attr(&'s str) -> &'s str,
-        text(&'s str).into(),
     }
 }
 
-#[cfg(feature = "compact_str")]
-dsl_impl! {
-    #[nxml]
-    impl Element {
-        attr(impl ToCompactString) -> CompactString,
-        text(impl ToCompactString).to_compact_string(),
-    }
-}
+impl<'e, S: NxmlStr> Rem<&str> for &'e GenericElement<S> {
+    type Output = &'e str;
 
-#[cfg(not(feature = "compact_str"))]
-dsl_impl! {
-    #[nxml]
-    impl Element {
-        attr(impl ToString) -> String,
-        text(impl ToString).to_string(),
+    /// A shorthand for getting an attribute value.
+    /// Not index because  `&element / "child" % "key"` is better
+    /// than `&(&element / "child")["key"]`.
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = nxml!(<Entity key="value"/>);
+    ///
+    /// assert_eq!(&element % "key", "value");
+    fn rem(self, rhs: &str) -> Self::Output {
+        match self.attr(rhs) {
+            Some(attr) => attr,
+            None => panic!("attribute '{rhs}' not found"),
+        }
     }
 }
 
-// Instead of duplicating the Display impl, lets abstract over accessors in 3x
-// the code xd
-// But the algorith is not duplicated, so discrepancies are not possible
-trait ElementAccessor: Sized {
-    fn name(&self) -> &str;
-    fn attributes(&self) -> impl Iterator<Item = (&str, &str)>;
-    fn children(&self) -> &[Self];
-    fn text_content(&self) -> &str;
-}
+impl<'e, S: NxmlStr> Rem<Text> for &'e GenericElement<S> {
+    type Output = &'e str;
 
-impl ElementAccessor for ElementRef<'_> {
-    fn name(&self) -> &str {
-        self.name
-    }
-    fn attributes(&self) -> impl Iterator<Item = (&str, &str)> {
-        self.attributes.iter().map(|(k, v)| (*k, *v))
-    }
-    fn children(&self) -> &[Self] {
-        &self.children
-    }
-    fn text_content(&self) -> &str {
-        &self.text_content
+    /// A shorthand for getting the text content.
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = nxml_ref!(<Entity>"hello"</Entity>);
+    ///
+    /// assert_eq!(&element % Text, "hello");
+    /// ```
+    fn rem(self, _: Text) -> Self::Output {
+        self.text_content.as_ref()
     }
 }
 
-impl ElementAccessor for Element {
-    fn name(&self) -> &str {
-        &self.name
-    }
-    fn attributes(&self) -> impl Iterator<Item = (&str, &str)> {
-        self.attributes
-            .iter()
-            .map(|(k, v)| (k.as_str(), v.as_str()))
-    }
-    fn children(&self) -> &[Self] {
-        &self.children
-    }
-    fn text_content(&self) -> &str {
-        &self.text_content
+impl<S: NxmlStr> Display for GenericElement<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.display().compact().fmt(f)
     }
 }
 
@@ -542,23 +564,28 @@ impl<'a, E> PrettyDisplay<'a, E> {
         self.autoclose = false;
         self
     }
+}
 
-    fn write(&self, w: &mut fmt::Formatter, element: &E, indent: usize) -> fmt::Result
-    where
-        E: ElementAccessor,
-    {
-        write!(w, "{:indent$}<{}", "", element.name())?;
-
-        for (key, value) in element.attributes() {
-            write!(w, " {key}=\"{value}\"")?;
+impl<S: NxmlStr> PrettyDisplay<'_, GenericElement<S>> {
+    fn write(
+        &self,
+        w: &mut fmt::Formatter,
+        element: &GenericElement<S>,
+        indent: usize,
+    ) -> fmt::Result {
+        let name = element.name.as_ref();
+        write!(w, "{:indent$}<{name}", "")?;
+
+        for (key, value) in &element.attributes {
+            write!(w, " {}=\"{}\"", key.as_ref(), value.as_ref())?;
         }
 
-        let text_content = element.text_content();
-        if element.children().is_empty() && text_content.is_empty() {
+        let text_content = element.text_content.as_ref();
+        if element.children.is_empty() && text_content.is_empty() {
             if self.autoclose {
                 write!(w, "/>")?;
             } else {
-                write!(w, "></{}>", element.name())?;
+                write!(w, "></{name}>")?;
             }
             return Ok(());
         }
@@ -570,16 +597,16 @@ impl<'a, E> PrettyDisplay<'a, E> {
             write!(w, "{:indent$}{text_content}{}", "", self.line_separator)?;
         }
 
-        for child in element.children() {
+        for child in &element.children {
             self.write(w, child, indent + self.indent_width)?;
             write!(w, "{}", self.line_separator)?;
         }
 
-        write!(w, "{:indent$}</{}>", "", element.name())
+        write!(w, "{:indent$}</{name}>", "")
     }
 }
 
-impl<'a, E: ElementAccessor> Display for PrettyDisplay<'a, E> {
+impl<S: NxmlStr> Display for PrettyDisplay<'_, GenericElement<S>> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         self.write(f, self.element, 0)
     }
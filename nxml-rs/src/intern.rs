@@ -0,0 +1,156 @@
+use std::{
+    borrow::Borrow,
+    collections::HashSet,
+    fmt::{self, Debug, Display},
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+/// An interned element or attribute name.
+///
+/// With the `intern` feature enabled, this is the type of [`Element::name`]
+/// and of the attribute keys of an [`Element`], so that every
+/// `<LuaComponent>` and every `script_source_file` in all of the loaded files
+/// share a single allocation.
+///
+/// The names are interned into a global table that is never cleared, which
+/// is fine since they are drawn from a small vocabulary - the values and the
+/// text are not interned.
+///
+/// Two names are equal only if they are the same allocation, so comparing
+/// them is a pointer comparison.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let a = parse("<LuaComponent script_source_file=\"a.lua\"/>").unwrap().to_owned();
+/// let b = nxml!(<LuaComponent script_source_file="b.lua"/>);
+///
+/// assert_eq!(a.name, b.name);
+/// assert!(std::ptr::eq(a.name.as_str(), b.name.as_str()));
+/// ```
+///
+/// [`Element`]: crate::Element
+/// [`Element::name`]: crate::Element::name
+#[derive(Clone)]
+pub struct Name(Arc<str>);
+
+static NAMES: OnceLock<Mutex<HashSet<Arc<str>>>> = OnceLock::new();
+
+impl Name {
+    /// Intern the given string, or get the existing name for it.
+    pub fn new(name: &str) -> Self {
+        let mut names = NAMES
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(interned) = names.get(name) {
+            return Self(interned.clone());
+        }
+        let interned: Arc<str> = name.into();
+        names.insert(interned.clone());
+        Self(interned)
+    }
+
+    /// The name as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Name {}
+
+impl Hash for Name {
+    // must be the same as for str, so that maps can be queried with a &str
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl PartialOrd for Name {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Name {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Name {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Name {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for Name {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Name {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<Name> for &str {
+    fn eq(&self, other: &Name) -> bool {
+        *self == other.as_str()
+    }
+}
+
+impl From<&str> for Name {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Self {
+        Self::new(&name)
+    }
+}
+
+#[cfg(feature = "compact_str")]
+impl From<compact_str::CompactString> for Name {
+    fn from(name: compact_str::CompactString) -> Self {
+        Self::new(&name)
+    }
+}
+
+impl Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(self.as_str(), f)
+    }
+}
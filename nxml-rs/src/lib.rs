@@ -1,10 +1,51 @@
 #![doc = include_str!(env!("README_PATH"))]
 #![deny(missing_debug_implementations)]
 
+mod accessor;
+#[cfg(feature = "rayon")]
+mod batch;
+mod cst;
+mod cursor;
+mod decode;
+mod diagnostic;
+mod document;
+mod editor;
 mod element;
+#[cfg(feature = "intern")]
+mod intern;
+#[cfg(feature = "small_map")]
+mod map;
+mod node;
+mod options;
 mod parser;
-mod tokenizer;
+mod reader;
+mod select;
+pub mod tokenizer;
+mod traverse;
+mod tree;
+mod visit;
 
+pub use accessor::*;
+#[cfg(feature = "rayon")]
+pub use batch::*;
+pub use cst::*;
+pub use cursor::*;
+pub use decode::*;
+pub use diagnostic::*;
+pub use document::*;
+pub use editor::*;
 pub use element::*;
+#[cfg(feature = "intern")]
+pub use intern::*;
+#[cfg(feature = "small_map")]
+pub use map::*;
+pub use node::*;
 pub use nxml_rs_macros::*;
+pub use options::*;
 pub use parser::*;
+pub use reader::*;
+pub use select::*;
+pub use tokenizer::{Position, Span};
+pub use traverse::*;
+pub use tree::*;
+pub use visit::*;
@@ -0,0 +1,402 @@
+use std::{
+    borrow::Borrow,
+    fmt::{self, Debug},
+    ops::Index,
+};
+
+use arrayvec::ArrayVec;
+
+/// How many attributes are stored inline, without a heap allocation.
+const INLINE: usize = 4;
+
+/// An insertion-ordered map stored as a small vector of pairs, with a
+/// linear lookup.
+///
+/// With the `small_map` feature enabled, this is the type of the attribute
+/// maps. Elements rarely have more than a few attributes, which are looked
+/// up by comparing the keys one by one, and the first few are stored inline
+/// with no allocation at all.
+///
+/// It has the parts of the [`IndexMap`](https://docs.rs/indexmap) API that
+/// make sense for attributes, and like it, two maps are equal if they have
+/// the same entries, regardless of the order.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let element = parse(r#"<Entity b="1" a="2" c="3" a="4"/>"#).unwrap();
+///
+/// let keys = element.attributes.keys().copied().collect::<Vec<_>>();
+///
+/// assert_eq!(keys, ["b", "a", "c"]);
+/// assert_eq!(element.attributes["a"], "4");
+/// ```
+#[derive(Clone)]
+pub struct SmallMap<K, V> {
+    entries: Entries<K, V>,
+}
+
+// not using smallvec, as it is invariant over the item type
+#[derive(Clone)]
+enum Entries<K, V> {
+    Inline(ArrayVec<(K, V), INLINE>),
+    Heap(Vec<(K, V)>),
+}
+
+impl<K, V> SmallMap<K, V> {
+    /// Create an empty map.
+    pub fn new() -> Self {
+        Self {
+            entries: Entries::Inline(ArrayVec::new()),
+        }
+    }
+
+    /// Create an empty map with room for at least `capacity` entries.
+    pub fn with_capacity(capacity: usize) -> Self {
+        if capacity <= INLINE {
+            return Self::new();
+        }
+        Self {
+            entries: Entries::Heap(Vec::with_capacity(capacity)),
+        }
+    }
+
+    /// Create a map out of entries that are known to have no repeated keys.
+    pub(crate) fn from_unique(entries: impl ExactSizeIterator<Item = (K, V)>) -> Self {
+        if entries.len() <= INLINE {
+            return Self {
+                entries: Entries::Inline(entries.collect()),
+            };
+        }
+        Self {
+            entries: Entries::Heap(entries.collect()),
+        }
+    }
+
+    fn as_slice(&self) -> &[(K, V)] {
+        match &self.entries {
+            Entries::Inline(entries) => entries,
+            Entries::Heap(entries) => entries,
+        }
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [(K, V)] {
+        match &mut self.entries {
+            Entries::Inline(entries) => entries,
+            Entries::Heap(entries) => entries,
+        }
+    }
+
+    /// The number of entries.
+    pub fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    /// Whether there are no entries.
+    pub fn is_empty(&self) -> bool {
+        self.as_slice().is_empty()
+    }
+
+    /// Remove all of the entries.
+    pub fn clear(&mut self) {
+        match &mut self.entries {
+            Entries::Inline(entries) => entries.clear(),
+            Entries::Heap(entries) => entries.clear(),
+        }
+    }
+
+    /// Iterate over the entries in insertion order.
+    pub fn iter(&self) -> SmallMapIter<'_, K, V> {
+        SmallMapIter(self.as_slice().iter())
+    }
+
+    /// Iterate over the entries in insertion order, with mutable values.
+    pub fn iter_mut(&mut self) -> SmallMapIterMut<'_, K, V> {
+        SmallMapIterMut(self.as_mut_slice().iter_mut())
+    }
+
+    /// Iterate over the keys in insertion order.
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.as_slice().iter().map(|(k, _)| k)
+    }
+
+    /// Iterate over the values in insertion order.
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.as_slice().iter().map(|(_, v)| v)
+    }
+
+    fn position<Q>(&self, key: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.as_slice().iter().position(|(k, _)| k.borrow() == key)
+    }
+
+    /// Get the value for the key.
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.position(key).map(|i| &self.as_slice()[i].1)
+    }
+
+    /// Get the value for the key, mutable version.
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.position(key).map(|i| &mut self.as_mut_slice()[i].1)
+    }
+
+    /// Whether there is a value for the key.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.position(key).is_some()
+    }
+
+    /// Insert the value for the key, returning the previous one.
+    ///
+    /// A key that is already present keeps its position.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    where
+        K: Eq,
+    {
+        if let Some(i) = self.position(&key) {
+            return Some(std::mem::replace(&mut self.as_mut_slice()[i].1, value));
+        }
+        match &mut self.entries {
+            Entries::Inline(entries) => {
+                if let Err(full) = entries.try_push((key, value)) {
+                    let mut spilled = Vec::with_capacity(INLINE * 2);
+                    spilled.extend(entries.drain(..));
+                    spilled.push(full.element());
+                    self.entries = Entries::Heap(spilled);
+                }
+            }
+            Entries::Heap(entries) => entries.push((key, value)),
+        }
+        None
+    }
+
+    /// Remove the value for the key, keeping the order of the rest.
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        let i = self.position(key)?;
+        let (_, value) = match &mut self.entries {
+            Entries::Inline(entries) => entries.remove(i),
+            Entries::Heap(entries) => entries.remove(i),
+        };
+        Some(value)
+    }
+
+    /// Same as [`remove`](Self::remove), named like the one of `IndexMap`.
+    pub fn shift_remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Eq + ?Sized,
+    {
+        self.remove(key)
+    }
+}
+
+impl<K, V> Default for SmallMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Debug, V: Debug> Debug for SmallMap<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<K: Eq, V: PartialEq> PartialEq for SmallMap<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(k, v)| other.get(k) == Some(v))
+    }
+}
+
+impl<K: Eq, V: Eq> Eq for SmallMap<K, V> {}
+
+impl<K, V, Q> Index<&Q> for SmallMap<K, V>
+where
+    K: Borrow<Q>,
+    Q: Eq + ?Sized,
+{
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.get(key).expect("key not found in the map")
+    }
+}
+
+impl<K: Eq, V> FromIterator<(K, V)> for SmallMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<K: Eq, V> Extend<(K, V)> for SmallMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K, V> IntoIterator for SmallMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = SmallMapIntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SmallMapIntoIter(match self.entries {
+            Entries::Inline(entries) => IntoIterInner::Inline(entries.into_iter()),
+            Entries::Heap(entries) => IntoIterInner::Heap(entries.into_iter()),
+        })
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a SmallMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = SmallMapIter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K, V> IntoIterator for &'a mut SmallMap<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = SmallMapIterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// An iterator over the entries of a [`SmallMap`].
+#[derive(Debug, Clone)]
+pub struct SmallMapIter<'a, K, V>(std::slice::Iter<'a, (K, V)>);
+
+impl<'a, K, V> Iterator for SmallMapIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for SmallMapIter<'_, K, V> {}
+
+impl<K, V> DoubleEndedIterator for SmallMapIter<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, v)| (k, v))
+    }
+}
+
+/// A mutable iterator over the entries of a [`SmallMap`].
+#[derive(Debug)]
+pub struct SmallMapIterMut<'a, K, V>(std::slice::IterMut<'a, (K, V)>);
+
+impl<'a, K, V> Iterator for SmallMapIterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (&*k, v))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<K, V> ExactSizeIterator for SmallMapIterMut<'_, K, V> {}
+
+impl<K, V> DoubleEndedIterator for SmallMapIterMut<'_, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back().map(|(k, v)| (&*k, v))
+    }
+}
+
+/// An owning iterator over the entries of a [`SmallMap`].
+#[derive(Debug, Clone)]
+pub struct SmallMapIntoIter<K, V>(IntoIterInner<K, V>);
+
+#[derive(Debug, Clone)]
+enum IntoIterInner<K, V> {
+    Inline(arrayvec::IntoIter<(K, V), INLINE>),
+    Heap(std::vec::IntoIter<(K, V)>),
+}
+
+impl<K, V> Iterator for SmallMapIntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.0 {
+            IntoIterInner::Inline(iter) => iter.next(),
+            IntoIterInner::Heap(iter) => iter.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match &self.0 {
+            IntoIterInner::Inline(iter) => iter.size_hint(),
+            IntoIterInner::Heap(iter) => iter.size_hint(),
+        }
+    }
+}
+
+impl<K, V> ExactSizeIterator for SmallMapIntoIter<K, V> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spills_to_the_heap_in_order() {
+        let mut map = (0..INLINE).map(|i| (i, i)).collect::<SmallMap<_, _>>();
+        assert!(matches!(map.entries, Entries::Inline(_)));
+
+        map.insert(INLINE, INLINE);
+        map.insert(1, 10);
+        assert!(matches!(map.entries, Entries::Heap(_)));
+
+        assert_eq!(map.remove(&0), Some(0));
+        assert_eq!(map.get(&1), Some(&10));
+        assert_eq!(
+            map.into_iter().collect::<Vec<_>>(),
+            [(1, 10), (2, 2), (3, 3), (4, 4)]
+        );
+    }
+
+    #[test]
+    fn equality_ignores_order() {
+        let a = [("a", "1"), ("b", "2")]
+            .into_iter()
+            .collect::<SmallMap<_, _>>();
+        let b = [("b", "2"), ("a", "1")]
+            .into_iter()
+            .collect::<SmallMap<_, _>>();
+        let c = [("b", "2"), ("a", "3")]
+            .into_iter()
+            .collect::<SmallMap<_, _>>();
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+}
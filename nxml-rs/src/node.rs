@@ -0,0 +1,267 @@
+use std::{
+    borrow::Cow,
+    fmt::{self, Display},
+};
+
+use crate::{
+    cst::{Cst, CstElement, CstNode},
+    element::{Element, ElementRef, Map},
+    parser::{push_text, NxmlError},
+    tokenizer::{Token, Tokenizer},
+};
+
+/// An element that keeps its text and child elements in the order they
+/// appear in the source, instead of concatenating all of the text into
+/// [`ElementRef::text_content`].
+///
+/// This is what a standard XML parser would produce (minus the Noita
+/// quirks of the markup itself), and is meant for consumers other than
+/// Noita, that care about where the text is relative to the child elements.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let element = NodeElement::parse("<a>hello <b/>big  world</a>").unwrap();
+///
+/// assert_eq!(element.children, [
+///     Node::Text("hello".into()),
+///     Node::Element(NodeElement::new("b")),
+///     Node::Text("big  world".into()),
+/// ]);
+/// assert_eq!(element.to_element_ref(), nxml_ref!(<a>"hello bigworld"<b/></a>));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeElement<'s> {
+    /// The name of the element.
+    pub name: &'s str,
+    /// A map of element attributes, same as [`ElementRef::attributes`].
+    pub attributes: Map<&'s str, &'s str>,
+    /// The content of the element, in the source order.
+    pub children: Vec<Node<'s>>,
+}
+
+/// A node of the content of a [`NodeElement`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Node<'s> {
+    /// A child element.
+    Element(NodeElement<'s>),
+    /// A run of text between child elements (or comments), as it is in the
+    /// source, without the leading and trailing whitespace.
+    ///
+    /// When converted from an [`ElementRef`] or an [`Element`], which do not
+    /// know where their text was, this is instead the whole joined
+    /// [`text_content`](ElementRef::text_content), as the only text node
+    /// before the child elements.
+    Text(Cow<'s, str>),
+    /// A comment, including the `<!--` and `-->`.
+    ///
+    /// Only present when converted from a [`Cst`], which is lossless.
+    Comment(&'s str),
+}
+
+impl<'s> NodeElement<'s> {
+    /// Create a new element with the given name.
+    pub fn new(name: &'s str) -> Self {
+        Self {
+            name,
+            attributes: Map::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Parse the source, see [`parse`](crate::parse).
+    ///
+    /// Comments are skipped, use [`Cst::to_node_element`] to keep them.
+    pub fn parse(s: &'s str) -> Result<Self, NxmlError> {
+        let cst = Cst::parse(s)?;
+        let root = cst
+            .root()
+            .expect("the parser always produces a root element");
+        Ok(Self::from_cst(root, false))
+    }
+
+    /// A shorthand for getting an attribute value.
+    pub fn attr(&self, key: &str) -> Option<&'s str> {
+        self.attributes.get(key).copied()
+    }
+
+    /// Iterate over the child elements, skipping the text and comments.
+    pub fn elements(&self) -> impl Iterator<Item = &NodeElement<'s>> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            _ => None,
+        })
+    }
+
+    /// Convert to an [`ElementRef`], joining the text the way Noita does.
+    ///
+    /// For an element that was parsed from a source, this is the same as
+    /// what [`parse`](crate::parse) would return.
+    pub fn to_element_ref(&self) -> ElementRef<'_> {
+        let mut element = ElementRef::new(self.name);
+        element.attributes = self.attributes.clone();
+        for node in &self.children {
+            match node {
+                Node::Element(child) => element.children.push(child.to_element_ref()),
+                Node::Text(text) => {
+                    let mut tokenizer = Tokenizer::new(text);
+                    loop {
+                        match tokenizer.next_token_spanned().0 {
+                            Token::Eof => break,
+                            token => push_text(&mut element.text_content, token.as_str()),
+                        }
+                    }
+                }
+                Node::Comment(_) => {}
+            }
+        }
+        element
+    }
+
+    /// Convert to an owned [`Element`], see
+    /// [`to_element_ref`](Self::to_element_ref).
+    pub fn to_owned(&self) -> Element {
+        self.to_element_ref().to_owned()
+    }
+
+    fn from_cst(element: &CstElement<'s>, comments: bool) -> Self {
+        let mut result = NodeElement::new(element.open_tag.name);
+        for attribute in &element.open_tag.attributes {
+            result.attributes.insert(attribute.key, attribute.value);
+        }
+
+        // the text tokens of the current run, with the whitespace between
+        let mut run: Vec<&'s str> = Vec::new();
+        let mut whitespace: Vec<&'s str> = Vec::new();
+        let flush = |run: &mut Vec<&'s str>, children: &mut Vec<Node<'s>>| {
+            match run[..] {
+                [] => return,
+                [text] => children.push(Node::Text(Cow::Borrowed(text))),
+                _ => children.push(Node::Text(Cow::Owned(run.concat()))),
+            }
+            run.clear();
+        };
+
+        for node in &element.children {
+            match node {
+                CstNode::Text { raw, .. } => {
+                    if !run.is_empty() {
+                        run.append(&mut whitespace);
+                    }
+                    run.push(raw);
+                }
+                CstNode::Whitespace(s) => whitespace.push(s),
+                CstNode::Element(child) => {
+                    flush(&mut run, &mut result.children);
+                    let child = NodeElement::from_cst(child, comments);
+                    result.children.push(Node::Element(child));
+                }
+                CstNode::Comment(comment) if comments => {
+                    flush(&mut run, &mut result.children);
+                    result.children.push(Node::Comment(comment));
+                }
+                _ => continue,
+            }
+            if !matches!(node, CstNode::Whitespace(_)) {
+                whitespace.clear();
+            }
+        }
+        flush(&mut run, &mut result.children);
+        result
+    }
+}
+
+impl<'s> Cst<'s> {
+    /// The root element as a [`NodeElement`], keeping the comments.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let cst = Cst::parse("<Entity><!-- the sprite --><Sprite/></Entity>").unwrap();
+    ///
+    /// let root = cst.to_node_element().unwrap();
+    ///
+    /// assert_eq!(root.children[0], Node::Comment("<!-- the sprite -->"));
+    /// ```
+    pub fn to_node_element(&self) -> Option<NodeElement<'s>> {
+        self.root().map(|root| NodeElement::from_cst(root, true))
+    }
+}
+
+impl<'s> From<ElementRef<'s>> for NodeElement<'s> {
+    /// Convert an [`ElementRef`], putting its text before the child
+    /// elements, same as it is displayed.
+    fn from(element: ElementRef<'s>) -> Self {
+        let mut children = Vec::with_capacity(element.children.len() + 1);
+        if !element.text_content.is_empty() {
+            children.push(Node::Text(element.text_content));
+        }
+        children.extend(
+            element
+                .children
+                .into_iter()
+                .map(|child| Node::Element(child.into())),
+        );
+        Self {
+            name: element.name,
+            attributes: element.attributes,
+            children,
+        }
+    }
+}
+
+impl<'a> From<&'a Element> for NodeElement<'a> {
+    fn from(element: &'a Element) -> Self {
+        element.as_ref().into()
+    }
+}
+
+impl Display for NodeElement<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "<{}", self.name)?;
+        for (key, value) in &self.attributes {
+            write!(f, " {key}=\"{value}\"")?;
+        }
+        if self.children.is_empty() {
+            return f.write_str("/>");
+        }
+        f.write_str(">")?;
+        for child in &self.children {
+            child.fmt(f)?;
+        }
+        write!(f, "</{}>", self.name)
+    }
+}
+
+impl Display for Node<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Node::Element(element) => element.fmt(f),
+            Node::Text(text) => f.write_str(text),
+            Node::Comment(comment) => f.write_str(comment),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrips_through_element_ref() {
+        let source =
+            "<a x=\"1\">\n  one \"two\"  three\n  <b>four</b>\n  <!-- c -->\n  five=six\n</a>";
+
+        let element = NodeElement::parse(source).unwrap();
+
+        assert_eq!(
+            element.to_string(),
+            "<a x=\"1\">one \"two\"  three<b>four</b>five=six</a>"
+        );
+        assert_eq!(element.to_element_ref(), crate::parse(source).unwrap());
+        assert_eq!(
+            NodeElement::from(element.to_element_ref()).to_element_ref(),
+            element.to_element_ref()
+        );
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+};
+
+use crate::{
+    element::ElementRef,
+    parser::{ElementSpans, NxmlErr, NxmlError, Parser},
+    reader::Attribute,
+};
+
+/// Configurable parsing, for when [`parse`](crate::parse) and friends are not
+/// enough.
+///
+/// By default, it parses exactly like [`parse`](crate::parse) does, with only
+/// the [`DEFAULT_MAX_DEPTH`](Self::DEFAULT_MAX_DEPTH) limit.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let hostile = "<a>".repeat(100_000);
+///
+/// let err = ParseOptions::new().max_depth(64).parse(&hostile).unwrap_err();
+///
+/// assert!(matches!(err.err, NxmlErr::LimitExceeded { limit: Limit::Depth, max: 64 }));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ParseOptions {
+    pub(crate) lenient: bool,
+    pub(crate) spans: bool,
+    pub(crate) warnings: bool,
+    pub(crate) duplicates: DuplicatePolicy,
+    pub(crate) max_depth: Option<usize>,
+    pub(crate) max_input_size: Option<usize>,
+    pub(crate) max_attributes: Option<usize>,
+    pub(crate) max_children: Option<usize>,
+}
+
+/// One of the limits of [`ParseOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    /// See [`ParseOptions::max_depth`].
+    Depth,
+    /// See [`ParseOptions::max_input_size`].
+    InputSize,
+    /// See [`ParseOptions::max_attributes`].
+    Attributes,
+    /// See [`ParseOptions::max_children`].
+    Children,
+}
+
+impl Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Limit::Depth => "nesting depth",
+            Limit::InputSize => "input size",
+            Limit::Attributes => "attributes per element",
+            Limit::Children => "children per element",
+        })
+    }
+}
+
+/// What to do with repeated attributes, see [`ParseOptions::duplicates`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum DuplicatePolicy {
+    /// Keep the last value, which is what Noita does.
+    ///
+    /// With the `indexmap` or `small_map` feature the attribute stays in the
+    /// position of the first occurrence.
+    #[default]
+    KeepLast,
+    /// Keep the first value.
+    KeepFirst,
+    /// Treat a repeated attribute as an error.
+    ///
+    /// In [`lenient`](ParseOptions::lenient) mode the error is recorded and
+    /// the last value is kept.
+    Error,
+    /// Keep every value, in [`Parsed::multimap`].
+    ///
+    /// The element itself holds the last value, same as with
+    /// [`KeepLast`](Self::KeepLast).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let source = r#"<Entity><Base file="a.xml" tags="x" file="b.xml"/></Entity>"#;
+    ///
+    /// let parsed = ParseOptions::new().duplicates(DuplicatePolicy::PreserveAll).parse(source).unwrap();
+    /// let multimap = parsed.multimap.unwrap();
+    ///
+    /// assert_eq!(&parsed.root / "Base" % "file", "b.xml");
+    /// assert_eq!(multimap.get_all(&[0], "file").collect::<Vec<_>>(), ["a.xml", "b.xml"]);
+    /// assert_eq!(multimap.get_all(&[0], "tags").collect::<Vec<_>>(), ["x"]);
+    /// assert_eq!(multimap.attributes(&[0]).len(), 3);
+    /// ```
+    PreserveAll,
+}
+
+/// Every attribute of every element, with the repeated ones kept, see
+/// [`DuplicatePolicy::PreserveAll`].
+///
+/// The elements are addressed by paths of child indices, same as in
+/// [`Editor`](crate::Editor).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AttributeMultimap<'s> {
+    attributes: HashMap<Vec<usize>, Vec<(&'s str, &'s str)>>,
+}
+
+impl<'s> AttributeMultimap<'s> {
+    pub(crate) fn insert(&mut self, path: Vec<usize>, attributes: &[Attribute<'s>]) {
+        if !attributes.is_empty() {
+            let attributes = attributes.iter().map(|a| (a.key, a.value)).collect();
+            self.attributes.insert(path, attributes);
+        }
+    }
+
+    /// Every attribute of the element at the path, in the order they appear
+    /// in the source, repeats included.
+    ///
+    /// Returns an empty slice if there is no element at the path.
+    pub fn attributes(&self, path: &[usize]) -> &[(&'s str, &'s str)] {
+        self.attributes.get(path).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every value of an attribute of the element at the path, in the order
+    /// they appear in the source.
+    pub fn get_all<'a>(
+        &'a self,
+        path: &[usize],
+        key: &'a str,
+    ) -> impl Iterator<Item = &'s str> + 'a {
+        self.attributes(path)
+            .iter()
+            .filter(move |(k, _)| *k == key)
+            .map(|(_, v)| *v)
+    }
+}
+
+/// An attribute that is repeated in an element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateAttribute<'s> {
+    /// The path of child indices to the element, see
+    /// [`Editor`](crate::Editor).
+    pub path: Vec<usize>,
+    /// The attribute name.
+    pub key: &'s str,
+    /// Every occurrence of the attribute, in the order they appear in the
+    /// source, with their values and locations.
+    pub occurrences: Vec<Attribute<'s>>,
+}
+
+/// The result of [`ParseOptions::parse`].
+#[derive(Debug, Clone)]
+pub struct Parsed<'s> {
+    /// The root element.
+    pub root: ElementRef<'s>,
+    /// Source locations of everything in the root element, if
+    /// [`spans`](ParseOptions::spans) were requested.
+    pub spans: Option<ElementSpans>,
+    /// The errors that were recovered from in
+    /// [`lenient`](ParseOptions::lenient) mode.
+    pub errors: Vec<NxmlError>,
+    /// Places where Noita reads the source differently from a standard XML
+    /// parser, if [`warnings`](ParseOptions::warnings) were requested.
+    pub warnings: Vec<NxmlError>,
+    /// Every repeated attribute, regardless of the
+    /// [`duplicates`](ParseOptions::duplicates) policy.
+    pub duplicates: Vec<DuplicateAttribute<'s>>,
+    /// Every attribute of every element, if the
+    /// [`duplicates`](ParseOptions::duplicates) policy is
+    /// [`PreserveAll`](DuplicatePolicy::PreserveAll).
+    pub multimap: Option<AttributeMultimap<'s>>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            lenient: false,
+            spans: false,
+            warnings: false,
+            duplicates: DuplicatePolicy::default(),
+            max_depth: Some(Self::DEFAULT_MAX_DEPTH),
+            max_input_size: None,
+            max_attributes: None,
+            max_children: None,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// The nesting depth limit that everything parses with unless told
+    /// otherwise.
+    ///
+    /// Dropping, cloning and displaying elements recurse into the children,
+    /// so a much deeper tree could overflow the stack of a thread, while
+    /// Noita files hardly go past a few dozen levels.
+    pub const DEFAULT_MAX_DEPTH: usize = 1024;
+
+    /// Same as [`ParseOptions::default`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Recover from errors instead of stopping at the first one, same as
+    /// [`parse_lenient`](crate::parse_lenient) does.
+    ///
+    /// Exceeding a limit is still an error even in lenient mode.
+    pub fn lenient(mut self) -> Self {
+        self.lenient = true;
+        self
+    }
+
+    /// Record the source locations of everything, same as
+    /// [`parse_spanned`](crate::parse_spanned) does.
+    pub fn spans(mut self) -> Self {
+        self.spans = true;
+        self
+    }
+
+    /// Report every place where Noita reads the source differently from
+    /// how a standard XML parser would, see [`Parsed::warnings`].
+    ///
+    /// The warnings are [`NxmlError`]s with a
+    /// [`Severity::Warning`](crate::Severity::Warning), they never stop the
+    /// parsing.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let source = "<Entity>\n  <Sprite image_file=\"a.png\"/>\n  big  dog\n";
+    ///
+    /// let parsed = ParseOptions::new().warnings().parse(source).unwrap();
+    ///
+    /// let codes = parsed.warnings.iter().map(|w| w.err.code()).collect::<Vec<_>>();
+    /// assert_eq!(codes, ["NXML0008", "NXML0011"]);
+    /// assert_eq!(
+    ///     parsed.warnings[0].to_string(),
+    ///     "Text is read as ' dog' instead of '  dog' [3:11]"
+    /// );
+    /// ```
+    pub fn warnings(mut self) -> Self {
+        self.warnings = true;
+        self
+    }
+
+    /// What to do with repeated attributes, which Noita allows but which are
+    /// most likely a mistake.
+    ///
+    /// They are recorded in [`Parsed::duplicates`] either way.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let source = r#"<Entity><DamageModelComponent hp="4" max_hp="4" hp="2"/></Entity>"#;
+    ///
+    /// let parsed = ParseOptions::new().duplicates(DuplicatePolicy::KeepFirst).parse(source).unwrap();
+    ///
+    /// assert_eq!(&parsed.root / "DamageModelComponent" % "hp", "4");
+    ///
+    /// let [duplicate] = &parsed.duplicates[..] else { panic!() };
+    /// assert_eq!(duplicate.path, [0]);
+    /// assert_eq!(duplicate.key, "hp");
+    /// assert_eq!(duplicate.occurrences[1].value, "2");
+    /// assert_eq!(duplicate.occurrences[1].key_span.start_pos(source).column, 49);
+    ///
+    /// let err = ParseOptions::new().duplicates(DuplicatePolicy::Error).parse(source).unwrap_err();
+    /// assert_eq!(err.err.code(), "NXML0014");
+    /// ```
+    pub fn duplicates(mut self, policy: DuplicatePolicy) -> Self {
+        self.duplicates = policy;
+        self
+    }
+
+    /// The maximum nesting depth of elements, the root element being at
+    /// depth 1.
+    ///
+    /// Defaults to [`DEFAULT_MAX_DEPTH`](Self::DEFAULT_MAX_DEPTH), raising
+    /// it is only safe with enough stack for the elements of that depth.
+    pub fn max_depth(mut self, max: usize) -> Self {
+        self.max_depth = Some(max);
+        self
+    }
+
+    /// The maximum size of the input, in bytes.
+    pub fn max_input_size(mut self, max: usize) -> Self {
+        self.max_input_size = Some(max);
+        self
+    }
+
+    /// The maximum number of attributes of a single element, counting the
+    /// repeated ones.
+    pub fn max_attributes(mut self, max: usize) -> Self {
+        self.max_attributes = Some(max);
+        self
+    }
+
+    /// The maximum number of child elements of a single element.
+    pub fn max_children(mut self, max: usize) -> Self {
+        self.max_children = Some(max);
+        self
+    }
+
+    /// Parse the source with these options.
+    pub fn parse<'s>(&self, s: &'s str) -> Result<Parsed<'s>, NxmlError> {
+        let mut parser = Parser::with_options(s, self);
+        let (root, spans) = parser.parse_inner()?;
+        let mut errors = parser.reader.take_errors();
+        // the lenient reader cuts the input off at a limit instead
+        let exceeded = errors
+            .iter()
+            .position(|e| matches!(e.err, NxmlErr::LimitExceeded { .. }));
+        if let Some(i) = exceeded {
+            return Err(errors.swap_remove(i));
+        }
+        Ok(Parsed {
+            root,
+            spans,
+            errors,
+            warnings: parser.reader.take_warnings(),
+            duplicates: parser.duplicates,
+            multimap: parser.multimap,
+        })
+    }
+}
@@ -1,13 +1,19 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::{hash_map, HashMap, HashSet},
+    ops::Range,
+};
 
 use thiserror::Error;
 
 use crate::{
-    element::ElementRef,
-    tokenizer::{Position, Token, Tokenizer},
+    element::{unique_map, ElementRef},
+    options::{AttributeMultimap, DuplicateAttribute, DuplicatePolicy, Limit, ParseOptions},
+    reader::{Attribute, Event, Reader, StartTag},
+    tokenizer::{Position, Span},
 };
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum NxmlErr {
     #[error("No closing '>' found for ending element </{element}>")]
     NoClosingSymbolFound { element: String },
@@ -23,149 +29,527 @@ pub enum NxmlErr {
     MissingAttributeValue { tag: String, attribute: String },
     #[error("Expected a name of the element after <")]
     MissingElementName,
+    #[error("Exceeded the maximum {limit} of {max}")]
+    LimitExceeded { limit: Limit, max: usize },
+    #[error("Text is read as '{}' instead of '{}'", read.escape_debug(), written.escape_debug())]
+    TextJoined { read: String, written: String },
+    #[error("Quoted string is not terminated, it runs to the end of the file")]
+    UnterminatedString,
+    #[error("NUL character is read as the end of the file")]
+    NulAsEof,
+    #[error("Element <{element}> is not closed before the end of the file")]
+    UnclosedAtEof { element: String },
+    #[error("'/' not followed by '>' is read as '>' in tag '{tag}'")]
+    SlashAsClose { tag: String },
+    #[error("Content after the root element is ignored")]
+    TrailingContent,
+    #[error("parsing tag '{tag}', attribute '{attribute}' is repeated")]
+    DuplicateAttribute { tag: String, attribute: String },
+}
+
+/// How bad an [`NxmlErr`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    /// The input is broken, but Noita tolerates it and most likely reads it
+    /// the way it was meant.
+    Warning,
+    /// The input is broken in a way that Noita either rejects or reads
+    /// differently from what was most likely meant.
+    Error,
+}
+
+impl NxmlErr {
+    /// A stable code identifying the kind of the error, e.g. `NXML0003`.
+    ///
+    /// The codes never change or get reused, even if the variants are
+    /// renamed or removed.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let err = parse("<Entity></Entyti>").unwrap_err();
+    ///
+    /// assert_eq!(err.err.code(), "NXML0003");
+    /// assert_eq!(err.err.severity(), Severity::Error);
+    /// assert!(err.err.help().contains("closes the current element anyway"));
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            NxmlErr::NoClosingSymbolFound { .. } => "NXML0001",
+            NxmlErr::NoOpeningSymbolFound => "NXML0002",
+            NxmlErr::MismatchedClosingTag { .. } => "NXML0003",
+            NxmlErr::MissingEqualsSign { .. } => "NXML0004",
+            NxmlErr::MissingAttributeValue { .. } => "NXML0005",
+            NxmlErr::MissingElementName => "NXML0006",
+            NxmlErr::LimitExceeded { .. } => "NXML0007",
+            NxmlErr::TextJoined { .. } => "NXML0008",
+            NxmlErr::UnterminatedString => "NXML0009",
+            NxmlErr::NulAsEof => "NXML0010",
+            NxmlErr::UnclosedAtEof { .. } => "NXML0011",
+            NxmlErr::SlashAsClose { .. } => "NXML0012",
+            NxmlErr::TrailingContent => "NXML0013",
+            NxmlErr::DuplicateAttribute { .. } => "NXML0014",
+        }
+    }
+
+    /// How bad the error is.
+    ///
+    /// The quirks reported with [`ParseOptions::warnings`] are all warnings,
+    /// and they are the only ones. Everything else is an error, which also
+    /// means it stops [`parse`] and is only recovered from by
+    /// [`parse_lenient`].
+    pub fn severity(&self) -> Severity {
+        match self {
+            NxmlErr::TextJoined { .. }
+            | NxmlErr::UnterminatedString
+            | NxmlErr::NulAsEof
+            | NxmlErr::UnclosedAtEof { .. }
+            | NxmlErr::SlashAsClose { .. }
+            | NxmlErr::TrailingContent => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    /// An explanation of how Noita itself reads the broken input, i.e. what
+    /// [`parse_lenient`] recovers to.
+    pub fn help(&self) -> &'static str {
+        match self {
+            NxmlErr::NoClosingSymbolFound { .. } => {
+                "Noita closes the element anyway, but drops whatever follows the name, and \
+                 anything after that up to the '>' becomes text of the parent element"
+            }
+            NxmlErr::NoOpeningSymbolFound => {
+                "Noita treats whatever comes first as if it was a '<', so the file has to \
+                 start with the root element (comments and whitespace before it are fine)"
+            }
+            NxmlErr::MismatchedClosingTag { .. } => {
+                "Noita closes the current element anyway, no matter the name, and the '>' \
+                 of this tag becomes text of the parent element"
+            }
+            NxmlErr::MissingEqualsSign { .. } => {
+                "Noita drops the attribute, along with whatever came instead of the '='"
+            }
+            NxmlErr::MissingAttributeValue { .. } => {
+                "Noita drops the attribute, along with the symbol that came instead of the \
+                 value, which can be the '>' closing the tag"
+            }
+            NxmlErr::MissingElementName => {
+                "Noita reads an element with an empty name, dropping whatever came instead \
+                 of the name"
+            }
+            NxmlErr::LimitExceeded { .. } => {
+                "Noita has no such limit, it was set with the ParseOptions to reject the \
+                 input"
+            }
+            NxmlErr::TextJoined { .. } => {
+                "Noita splits text on whitespace and on '<', '>', '=' and '/', removes the \
+                 quotes, and joins the second piece with a space and the rest with nothing"
+            }
+            NxmlErr::UnterminatedString => {
+                "Noita reads everything up to the end of the file as part of the string"
+            }
+            NxmlErr::NulAsEof => {
+                "Noita closes the current element at the NUL character, and keeps reading \
+                 the rest of the file as the content of its parent"
+            }
+            NxmlErr::UnclosedAtEof { .. } => {
+                "Noita closes every element that is still open at the end of the file, \
+                 while a standard XML parser rejects the file"
+            }
+            NxmlErr::SlashAsClose { .. } => {
+                "Noita ends the opening tag at the '/', and whatever follows it becomes the \
+                 content of the element"
+            }
+            NxmlErr::TrailingContent => {
+                "Noita stops reading after the root element is closed, while a standard XML \
+                 parser rejects the file"
+            }
+            NxmlErr::DuplicateAttribute { .. } => {
+                "Noita uses the last value of the attribute, but in the position of the first \
+                 one, and ignores the others"
+            }
+        }
+    }
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 #[error("{err} [{at}]")]
 pub struct NxmlError {
     pub err: NxmlErr,
     pub at: Position,
+    /// The byte range of the part of the source the error is about.
+    pub span: Range<usize>,
+    /// The byte range of the opening tag of the element that was being
+    /// closed, for [`NxmlErr::MismatchedClosingTag`] and
+    /// [`NxmlErr::NoClosingSymbolFound`].
+    pub open_tag: Option<Range<usize>>,
+}
+
+/// Source locations of everything that makes up a parsed element.
+///
+/// This is a tree parallel to the [`ElementRef`] returned alongside it by
+/// [`parse_spanned`], so e.g. `spans.children[1]` describes
+/// `element.children[1]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ElementSpans {
+    /// The opening tag, from the `<` to the closing `>` (or `/>` for
+    /// self-closing elements).
+    ///
+    /// If the tag was never closed (the file ended inside of it), the span
+    /// ends where the last token of it did.
+    pub open_tag: Span,
+    /// The name of the element in the opening tag.
+    pub name: Span,
+    /// Every attribute, in the order they appear in the source.
+    ///
+    /// Repeated attributes are all present here, even though only one of them
+    /// ends up in [`ElementRef::attributes`].
+    pub attributes: Vec<AttributeSpans>,
+    /// Every text token of the element, in the order they appear in the
+    /// source.
+    pub text: Vec<Span>,
+    /// The closing tag, e.g. `</Entity>`.
+    ///
+    /// This is `None` for self-closing elements and for elements that were
+    /// not closed at all, or only partially, before the end of the file.
+    pub close_tag: Option<Span>,
+    /// Spans of the child elements.
+    pub children: Vec<ElementSpans>,
+}
+
+/// Source locations of a single attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttributeSpans {
+    /// The attribute name.
+    pub key: Span,
+    /// The attribute value, including the quotes if it had them.
+    pub value: Span,
 }
 
-pub fn parse(s: &str) -> Result<ElementRef, NxmlError> {
+/// Parse the root element of the source, the same way Noita does.
+///
+/// Elements nested deeper than
+/// [`ParseOptions::DEFAULT_MAX_DEPTH`] levels are an
+/// [`NxmlErr::LimitExceeded`] error, use [`ParseOptions::max_depth`] to
+/// allow deeper ones.
+pub fn parse(s: &str) -> Result<ElementRef<'_>, NxmlError> {
     Parser::new(s).parse()
 }
 
-pub fn parse_lenient(s: &str) -> (ElementRef, Vec<NxmlError>) {
+/// Same as [`parse`], but recovers from the errors, returning them along
+/// with whatever it could read.
+///
+/// Past the [default depth limit](ParseOptions::DEFAULT_MAX_DEPTH) the rest
+/// of the input is dropped, with an [`NxmlErr::LimitExceeded`] error.
+pub fn parse_lenient(s: &str) -> (ElementRef<'_>, Vec<NxmlError>) {
     let mut parser = Parser::new(s).lenient();
     let element = parser.parse().expect("lenient parser never errors");
-    (element, parser.errors)
+    (element, parser.reader.take_errors())
+}
+
+/// Same as [`parse`], but also returns whatever non-whitespace and
+/// non-comment content follows the root element, which Noita silently
+/// ignores.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let (element, rest) = parse_with_remainder("<Entity/>\n<Entity/>\n").unwrap();
+///
+/// assert_eq!(element.name, "Entity");
+/// assert_eq!(rest, "<Entity/>\n");
+///
+/// let (_, rest) = parse_with_remainder("<Entity/>\n<!-- comment -->\n").unwrap();
+///
+/// assert!(rest.is_empty());
+/// ```
+pub fn parse_with_remainder(s: &str) -> Result<(ElementRef<'_>, &str), NxmlError> {
+    let mut parser = Parser::new(s);
+    let element = parser.parse()?;
+    Ok((element, parser.reader.remainder()))
+}
+
+/// Parse every top-level element, in order, instead of stopping after the
+/// first one.
+///
+/// This is a runtime equivalent of the [`nxml_refs!`](crate::nxml_refs) macro.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let elements = parse_all("<a/> <b>text</b> <c/>").unwrap();
+///
+/// assert_eq!(elements, nxml_refs!(<a/><b>text</b><c/>));
+/// ```
+pub fn parse_all(s: &str) -> Result<Vec<ElementRef<'_>>, NxmlError> {
+    let mut parser = Parser::new(s).fragment();
+    let mut elements = Vec::new();
+    while let Some((element, _)) = parser.next_root()? {
+        elements.push(element);
+    }
+    Ok(elements)
+}
+
+/// Lenient version of [`parse_all`], see [`parse_lenient`].
+pub fn parse_all_lenient(s: &str) -> (Vec<ElementRef<'_>>, Vec<NxmlError>) {
+    let mut parser = Parser::new(s).lenient().fragment();
+    let mut elements = Vec::new();
+    while let Some((element, _)) = parser.next_root().expect("lenient parser never errors") {
+        elements.push(element);
+    }
+    (elements, parser.reader.take_errors())
+}
+
+/// Same as [`parse`], but also records where in the source every part of
+/// every element is.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let source = "<Entity>\n  <LuaComponent script_source_file=\"a.lua\" />\n</Entity>";
+///
+/// let (element, spans) = parse_spanned(source).unwrap();
+///
+/// let lua = &spans.children[0];
+/// assert_eq!(&source[lua.name.range()], "LuaComponent");
+/// assert_eq!(&source[lua.attributes[0].value.range()], "\"a.lua\"");
+/// assert_eq!(lua.open_tag.start_pos(source), Position { line: 2, column: 3 });
+/// assert_eq!(&source[spans.close_tag.unwrap().range()], "</Entity>");
+/// ```
+pub fn parse_spanned(s: &str) -> Result<(ElementRef<'_>, ElementSpans), NxmlError> {
+    let mut parser = Parser::new(s);
+    parser.spans = true;
+    let (element, spans) = parser.parse_inner()?;
+    Ok((element, spans.expect("spans were requested")))
+}
+
+/// Appends a text token to the text content the way Noita does it.
+/// Up to how many attributes a tag has for the repeated ones to be looked
+/// for by comparing every pair, which beats hashing them when there are
+/// only a few.
+const LINEAR_SCAN_MAX: usize = 16;
+
+/// Whether any of the keys is repeated.
+pub(crate) fn has_repeats<'s>(mut keys: impl ExactSizeIterator<Item = &'s str> + Clone) -> bool {
+    if keys.len() <= LINEAR_SCAN_MAX {
+        while let Some(key) = keys.next() {
+            if keys.clone().any(|k| k == key) {
+                return true;
+            }
+        }
+        return false;
+    }
+    let mut seen = HashSet::with_capacity(keys.len());
+    !keys.all(|key| seen.insert(key))
+}
+
+/// Keep one value of every repeated key, according to the policy, in the
+/// position of the first occurrence.
+pub(crate) fn deduplicate<'s>(
+    attributes: impl Iterator<Item = (&'s str, &'s str)>,
+    policy: DuplicatePolicy,
+) -> Vec<(&'s str, &'s str)> {
+    let mut unique: Vec<(&str, &str)> = Vec::new();
+    let mut positions: HashMap<_, usize> = HashMap::new();
+    for (key, value) in attributes {
+        match positions.entry(key) {
+            hash_map::Entry::Occupied(position) => {
+                if policy != DuplicatePolicy::KeepFirst {
+                    unique[*position.get()].1 = value;
+                }
+            }
+            hash_map::Entry::Vacant(position) => {
+                position.insert(unique.len());
+                unique.push((key, value));
+            }
+        }
+    }
+    unique
+}
+
+pub(crate) fn push_text<'s>(text_content: &mut Cow<'s, str>, text: &'s str) {
+    match text_content {
+        Cow::Borrowed("") => *text_content = Cow::Borrowed(text),
+        Cow::Borrowed(content) => *text_content = Cow::Owned(content.to_owned() + " " + text),
+        Cow::Owned(s) => s.push_str(text),
+    }
 }
 
 #[derive(Debug)]
-struct Parser<'s> {
-    tokenizer: Tokenizer<'s>,
-    errors: Vec<NxmlError>,
-    lenient: bool,
+pub(crate) struct Parser<'s> {
+    pub(crate) reader: Reader<'s>,
+    spans: bool,
+    policy: DuplicatePolicy,
+    pub(crate) duplicates: Vec<DuplicateAttribute<'s>>,
+    pub(crate) multimap: Option<AttributeMultimap<'s>>,
 }
 
 impl<'s> Parser<'s> {
-    fn new(data: &str) -> Parser {
+    fn new(data: &str) -> Parser<'_> {
+        Parser {
+            reader: Reader::new(data),
+            spans: false,
+            policy: DuplicatePolicy::KeepLast,
+            duplicates: Vec::new(),
+            multimap: None,
+        }
+    }
+
+    pub(crate) fn with_options(data: &'s str, options: &ParseOptions) -> Self {
         Parser {
-            tokenizer: Tokenizer::new(data),
-            errors: Vec::new(),
-            lenient: false,
+            reader: Reader::with_options(data, options),
+            spans: options.spans,
+            policy: options.duplicates,
+            duplicates: Vec::new(),
+            multimap: (options.duplicates == DuplicatePolicy::PreserveAll)
+                .then(AttributeMultimap::default),
         }
     }
 
     fn lenient(mut self) -> Self {
-        self.lenient = true;
+        self.reader = self.reader.lenient();
         self
     }
 
-    fn report(&mut self, err: NxmlErr) -> Result<(), NxmlError> {
-        let error = NxmlError {
-            err,
-            at: self.tokenizer.position(),
-        };
-        if self.lenient {
-            self.errors.push(error);
-            return Ok(());
-        }
-        Err(error)
+    fn fragment(mut self) -> Self {
+        self.reader = self.reader.fragment();
+        self
     }
 
     fn parse(&mut self) -> Result<ElementRef<'s>, NxmlError> {
-        self.parse_inner(false)
+        self.parse_inner().map(|(element, _)| element)
     }
 
-    fn parse_inner(&mut self, skip_opening_tag: bool) -> Result<ElementRef<'s>, NxmlError> {
-        if !skip_opening_tag && !matches!(self.tokenizer.next_token(), Token::OpenLess) {
-            self.report(NxmlErr::NoOpeningSymbolFound)?;
-        }
+    pub(crate) fn parse_inner(
+        &mut self,
+    ) -> Result<(ElementRef<'s>, Option<ElementSpans>), NxmlError> {
+        Ok(self
+            .next_root()?
+            .expect("the reader always produces a root element"))
+    }
 
-        let name = match self.tokenizer.next_token() {
-            Token::String(name) => name,
-            _ => {
-                self.report(NxmlErr::MissingElementName)?;
-                ""
+    /// Creates the element for the opening tag, `path` being the path of
+    /// child indices to it.
+    fn start(
+        &mut self,
+        tag: StartTag<'s>,
+        path: impl FnOnce() -> Vec<usize>,
+    ) -> Result<(ElementRef<'s>, Option<ElementSpans>), NxmlError> {
+        let repeats = has_repeats(tag.attributes.iter().map(|a| a.key));
+        if repeats || self.multimap.is_some() {
+            let path = path();
+            if repeats {
+                self.check_duplicates(&tag, &path)?;
             }
+            if let Some(multimap) = &mut self.multimap {
+                multimap.insert(path, &tag.attributes);
+            }
+        }
+
+        let mut element = ElementRef::new(tag.name);
+        let spans = self.spans.then(|| ElementSpans {
+            open_tag: tag.span,
+            name: tag.name_span,
+            attributes: tag
+                .attributes
+                .iter()
+                .map(|a| AttributeSpans {
+                    key: a.key_span,
+                    value: a.value_span,
+                })
+                .collect(),
+            text: Vec::new(),
+            close_tag: None,
+            children: Vec::new(),
+        });
+        let attributes = tag.attributes.iter().map(|a| (a.key, a.value));
+        element.attributes = match repeats {
+            false => unique_map(attributes),
+            true => unique_map(deduplicate(attributes, self.policy).into_iter()),
         };
+        Ok((element, spans))
+    }
+
+    fn check_duplicates(&mut self, tag: &StartTag<'s>, path: &[usize]) -> Result<(), NxmlError> {
+        let attributes = &tag.attributes;
+        // the occurrences of every repeated key, in the order of the first
+        // occurrences, which is only worked out for the rare tags with any
+        let mut found: Vec<(&str, Vec<Attribute>)> = Vec::new();
+        let mut positions = HashMap::with_capacity(attributes.len());
+        for attribute in attributes {
+            let position = *positions.entry(attribute.key).or_insert_with(|| {
+                found.push((attribute.key, Vec::new()));
+                found.len() - 1
+            });
+            found[position].1.push(*attribute);
+        }
+        found.retain(|(_, occurrences)| occurrences.len() > 1);
+        for (key, occurrences) in found {
+            if self.policy == DuplicatePolicy::Error {
+                let err = NxmlErr::DuplicateAttribute {
+                    tag: tag.name.to_owned(),
+                    attribute: key.to_owned(),
+                };
+                let second = occurrences[1];
+                let span = second.key_span.to(second.value_span);
+                self.reader.report(self.reader.error(err, span))?;
+            }
+            self.duplicates.push(DuplicateAttribute {
+                path: path.to_vec(),
+                key,
+                occurrences,
+            });
+        }
+        Ok(())
+    }
 
-        let mut element = ElementRef::new(name);
+    /// Builds the next root element out of the reader events.
+    ///
+    /// This is done with an explicit stack instead of recursion, so that
+    /// deeply nested input can't overflow the call stack.
+    #[allow(clippy::type_complexity)]
+    fn next_root(&mut self) -> Result<Option<(ElementRef<'s>, Option<ElementSpans>)>, NxmlError> {
+        let mut stack: Vec<(ElementRef<'s>, Option<ElementSpans>)> = Vec::new();
 
-        loop {
-            match self.tokenizer.next_token() {
-                Token::Eof => return Ok(element),
-                Token::Slash => {
-                    if self.tokenizer.take('>') {
-                        return Ok(element);
-                    }
-                    break;
+        while let Some(event) = self.reader.next_event() {
+            let path = || stack.iter().map(|(e, _)| e.children.len()).collect();
+            let finished = match event? {
+                Event::StartElement(tag) => {
+                    let started = self.start(tag, path)?;
+                    stack.push(started);
+                    continue;
                 }
-                Token::CloseGreater => break,
-                Token::String(name) => {
-                    let Token::Equal = self.tokenizer.next_token() else {
-                        self.report(NxmlErr::MissingEqualsSign {
-                            tag: element.name.to_owned(),
-                            attribute: name.to_owned(),
-                        })?;
-                        continue;
-                    };
-
-                    let Token::String(value) = self.tokenizer.next_token() else {
-                        self.report(NxmlErr::MissingAttributeValue {
-                            tag: element.name.to_owned(),
-                            attribute: name.to_owned(),
-                        })?;
+                Event::SelfClose(tag) => self.start(tag, path)?,
+                Event::Text { text, span } => {
+                    let Some((element, spans)) = stack.last_mut() else {
                         continue;
                     };
-
-                    element.attributes.insert(name, value);
-                }
-                _ => (),
-            }
-        }
-        loop {
-            match self.tokenizer.next_token() {
-                Token::Eof => return Ok(element),
-                Token::OpenLess => (),
-                token => {
-                    match element.text_content {
-                        Cow::Borrowed("") => {
-                            element.text_content = Cow::Borrowed(token.as_str());
-                        }
-                        Cow::Borrowed(content) => {
-                            element.text_content =
-                                Cow::Owned(content.to_owned() + " " + token.as_str())
-                        }
-                        Cow::Owned(ref mut s) => s.push_str(token.as_str()),
+                    push_text(&mut element.text_content, text);
+                    if let Some(spans) = spans {
+                        spans.text.push(span);
                     }
                     continue;
                 }
-            }
-
-            if !self.tokenizer.take('/') {
-                element.children.push(self.parse_inner(true)?);
-                continue;
-            }
-
-            match self.tokenizer.next_token() {
-                Token::String(name) if name == element.name => {
-                    if let Token::CloseGreater = self.tokenizer.next_token() {
-                        return Ok(element);
+                Event::EndElement { span, .. } => {
+                    let Some(mut finished) = stack.pop() else {
+                        continue;
+                    };
+                    if let Some(spans) = &mut finished.1 {
+                        spans.close_tag = span;
                     }
-                    self.report(NxmlErr::NoClosingSymbolFound {
-                        element: name.to_owned(),
-                    })?;
+                    finished
                 }
-                token => self.report(NxmlErr::MismatchedClosingTag {
-                    expected: element.name.to_owned(),
-                    got: token.as_str().to_owned(),
-                })?,
             };
-            return Ok(element);
+
+            let Some((parent, parent_spans)) = stack.last_mut() else {
+                return Ok(Some(finished));
+            };
+            parent.children.push(finished.0);
+            if let (Some(spans), Some(child_spans)) = (parent_spans, finished.1) {
+                spans.children.push(child_spans);
+            }
         }
+        Ok(None)
     }
 }
 
@@ -178,4 +562,151 @@ mod tests {
         let err = parse("\"").unwrap_err();
         assert!(matches!(err.err, NxmlErr::NoOpeningSymbolFound));
     }
+
+    #[test]
+    fn limits_are_fatal_even_when_lenient() {
+        let options = ParseOptions::new().lenient();
+        let source = "<a><b x=\"1\" y=\"2\"/><c/><d><e/></d></a>";
+
+        let limit = |options: ParseOptions| match options.parse(source) {
+            Err(NxmlError {
+                err: NxmlErr::LimitExceeded { limit, .. },
+                ..
+            }) => Some(limit),
+            _ => None,
+        };
+
+        assert_eq!(limit(options), None);
+        assert_eq!(limit(options.max_depth(2)), Some(Limit::Depth));
+        assert_eq!(limit(options.max_depth(3)), None);
+        assert_eq!(limit(options.max_attributes(1)), Some(Limit::Attributes));
+        assert_eq!(limit(options.max_children(2)), Some(Limit::Children));
+        assert_eq!(limit(options.max_input_size(10)), Some(Limit::InputSize));
+    }
+
+    #[test]
+    fn deep_nesting_is_limited_by_default() {
+        let nested = |depth| {
+            format!(
+                "{}<a/>{}",
+                "<a>".repeat(depth - 1),
+                "</a>".repeat(depth - 1)
+            )
+        };
+        let max = ParseOptions::DEFAULT_MAX_DEPTH;
+
+        let err = parse(&nested(200_000)).unwrap_err();
+        assert!(matches!(
+            err.err,
+            NxmlErr::LimitExceeded { limit: Limit::Depth, max: m } if m == max
+        ));
+        assert!(parse(&nested(max + 1)).is_err());
+
+        // the deepest tree that parses can still be cloned, compared,
+        // displayed and dropped without overflowing the stack of a test
+        // thread
+        let source = nested(max);
+        let element = parse(&source).unwrap();
+        let owned = element.clone().to_owned();
+        assert_eq!(owned, element.to_owned());
+        assert_eq!(owned.to_string(), source);
+    }
+
+    #[test]
+    fn lenient_parsing_stops_at_the_limits() {
+        let source = format!("<a>{}x", "<b>".repeat(2000));
+        let exceeded = |errors: &[NxmlError]| {
+            matches!(
+                errors,
+                [NxmlError {
+                    err: NxmlErr::LimitExceeded {
+                        limit: Limit::Depth,
+                        ..
+                    },
+                    ..
+                }]
+            )
+        };
+
+        let (element, errors) = parse_lenient(&source);
+        assert!(exceeded(&errors));
+        let depth = std::iter::successors(Some(&element), |e| e.children.first()).count();
+        assert_eq!(depth, ParseOptions::DEFAULT_MAX_DEPTH);
+
+        let (elements, errors) = parse_all_lenient(&source);
+        assert!(exceeded(&errors) && elements.len() == 1);
+        assert!(exceeded(&crate::Tree::parse_lenient(&source).1));
+        assert!(exceeded(&crate::Cst::parse_lenient(&source).1));
+
+        let source = "<a><b x=\"1\" y=\"2\" z=\"3\"/><c/></a>";
+        let options = ParseOptions::new().max_attributes(1);
+        let mut parser = Parser::with_options(source, &options.lenient());
+        let (element, _) = parser.parse_inner().unwrap();
+        assert_eq!(element.to_string(), "<a><b x=\"1\"/></a>");
+    }
+
+    #[test]
+    fn finds_repeats_among_a_lot_of_attributes() {
+        let count = 60_000;
+        let mut source = String::from("<a");
+        for i in 0..count {
+            source += &format!(" k{i}=\"{i}\"");
+        }
+        source += " k7=\"x\" k3=\"y\" k7=\"z\"/>";
+
+        let parsed = ParseOptions::new().parse(&source).unwrap();
+
+        assert_eq!(parsed.root.attributes.len(), count);
+        assert_eq!(&parsed.root % "k7", "z");
+        let duplicates = parsed
+            .duplicates
+            .iter()
+            .map(|d| (d.key, d.occurrences.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(duplicates, [("k3", 2), ("k7", 3)]);
+
+        let tree = crate::Tree::parse(&source).unwrap();
+        assert_eq!(tree.root().attributes().len(), count);
+        assert_eq!(tree.root() % "k3", "y");
+    }
+
+    #[test]
+    fn lenient_duplicate_errors_keep_last() {
+        let source = r#"<a><b/><c><d x="1" y="2" x="3" y="4" x="5"/></c></a>"#;
+        let options = ParseOptions::new()
+            .lenient()
+            .duplicates(DuplicatePolicy::Error);
+
+        let parsed = options.parse(source).unwrap();
+
+        assert_eq!(&parsed.root / "c" / "d" % "x", "5");
+        assert_eq!(parsed.errors.len(), 2);
+        let duplicates = parsed
+            .duplicates
+            .iter()
+            .map(|d| (d.path.clone(), d.key, d.occurrences.len()))
+            .collect::<Vec<_>>();
+        assert_eq!(duplicates, [(vec![1, 0], "x", 3), (vec![1, 0], "y", 2)]);
+    }
+
+    #[test]
+    fn spans_cover_tags_and_text() {
+        let source = "<a x=y>hello <b/> world</a >";
+        let (_, spans) = parse_spanned(source).unwrap();
+
+        assert_eq!(&source[spans.open_tag.range()], "<a x=y>");
+        assert_eq!(&source[spans.attributes[0].key.range()], "x");
+        assert_eq!(&source[spans.attributes[0].value.range()], "y");
+        assert_eq!(&source[spans.children[0].open_tag.range()], "<b/>");
+        assert_eq!(spans.children[0].close_tag, None);
+        assert_eq!(
+            spans
+                .text
+                .iter()
+                .map(|s| &source[s.range()])
+                .collect::<Vec<_>>(),
+            ["hello", "world"]
+        );
+        assert_eq!(&source[spans.close_tag.unwrap().range()], "</a >");
+    }
 }
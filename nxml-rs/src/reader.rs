@@ -0,0 +1,683 @@
+use std::ops::Range;
+
+use crate::{
+    options::{Limit, ParseOptions},
+    parser::{NxmlErr, NxmlError},
+    tokenizer::{Span, Token, Tokenizer},
+};
+
+/// An opening tag, e.g. `<LuaComponent script_source_file="a.lua">`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartTag<'s> {
+    /// The name of the element.
+    pub name: &'s str,
+    /// The attributes in the order they appear in the source, including
+    /// repeated ones.
+    pub attributes: Vec<Attribute<'s>>,
+    /// The whole tag, from the `<` to the closing `>` (or `/>`).
+    ///
+    /// If the tag was never closed (the file ended inside of it), the span
+    /// ends where the last token of it did.
+    pub span: Span,
+    /// The name of the element.
+    pub name_span: Span,
+}
+
+impl<'s> StartTag<'s> {
+    /// A shorthand for getting an attribute value.
+    ///
+    /// If the attribute is repeated, the last value is returned, as that is
+    /// the one that ends up in the parsed element.
+    pub fn attr(&self, key: &str) -> Option<&'s str> {
+        self.attributes
+            .iter()
+            .rev()
+            .find(|a| a.key == key)
+            .map(|a| a.value)
+    }
+}
+
+/// A single attribute of a [`StartTag`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Attribute<'s> {
+    /// The attribute name.
+    pub key: &'s str,
+    /// The attribute value, without the quotes.
+    pub value: &'s str,
+    /// The attribute name.
+    pub key_span: Span,
+    /// The attribute value, including the quotes if it had them.
+    pub value_span: Span,
+}
+
+/// A single parsing event produced by the [`Reader`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event<'s> {
+    /// An opening tag of an element that has content, which ends with a
+    /// matching [`Event::EndElement`].
+    StartElement(StartTag<'s>),
+    /// A self-closing element, e.g. `<TestComponent />`.
+    ///
+    /// No [`Event::EndElement`] follows it.
+    SelfClose(StartTag<'s>),
+    /// A single text token.
+    ///
+    /// Noita splits bare text on whitespace (and on `<`, `>`, `=` and `/`),
+    /// so e.g. `<a>hello world</a>` produces two text events.
+    Text { text: &'s str, span: Span },
+    /// The end of an element opened by a previous [`Event::StartElement`].
+    EndElement {
+        /// The name of the element being closed.
+        name: &'s str,
+        /// The closing tag, or as much of it as was there in case of an
+        /// error.
+        ///
+        /// This is `None` when the element was not closed before the end of
+        /// the file.
+        span: Option<Span>,
+    },
+}
+
+/// An element that is currently open.
+#[derive(Debug, Clone, Copy)]
+struct Open<'s> {
+    name: &'s str,
+    tag: Span,
+    children: usize,
+    /// How many text tokens were joined into its text, up to 2, which is all
+    /// that matters for how the next one is joined.
+    text_tokens: u8,
+    /// Where the last text token ended, unless a child element came after
+    /// it.
+    text_end: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Start,
+    Content,
+    PendingEnd,
+    Done,
+}
+
+/// A pull parser, producing a stream of [`Event`]s instead of building the
+/// whole element tree.
+///
+/// It follows the same rules (and quirks) as [`parse`](crate::parse), which
+/// is in fact implemented on top of it.
+///
+/// Just like [`parse`](crate::parse), it stops after the root element is
+/// closed, unless [`fragment`](Self::fragment) is used.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let mut reader = Reader::new(r#"
+///     <Materials>
+///         <CellData name="air" />
+///         <CellData name="water" />
+///         <CellData name="lava" />
+///     </Materials>
+/// "#);
+///
+/// let water = reader.find_map(|event| match event {
+///     Ok(Event::SelfClose(tag)) if tag.attr("name") == Some("water") => Some(tag),
+///     _ => None,
+/// });
+///
+/// assert_eq!(water.unwrap().name, "CellData");
+/// ```
+#[derive(Debug)]
+pub struct Reader<'s> {
+    tokenizer: Tokenizer<'s>,
+    stack: Vec<Open<'s>>,
+    state: State,
+    options: ParseOptions,
+    fragment: bool,
+    errors: Vec<NxmlError>,
+    warnings: Vec<NxmlError>,
+    /// A cleared attribute list to reuse for the next tag, see
+    /// [`recycle`](Self::recycle).
+    spare: Vec<Attribute<'s>>,
+}
+
+impl<'s> Reader<'s> {
+    /// Create a reader over the given source text.
+    pub fn new(data: &'s str) -> Self {
+        Self::with_options(data, &ParseOptions::default())
+    }
+
+    /// Create a reader that follows the given options.
+    ///
+    /// Only the limits and the leniency are relevant here, the reader always
+    /// reports the spans.
+    pub fn with_options(data: &'s str, options: &ParseOptions) -> Self {
+        Self {
+            tokenizer: Tokenizer::new(data),
+            stack: Vec::new(),
+            state: State::Start,
+            options: *options,
+            fragment: false,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            spare: Vec::new(),
+        }
+    }
+
+    /// Make the reader recover from errors instead of stopping at the first
+    /// one, same as [`parse_lenient`](crate::parse_lenient) does.
+    ///
+    /// The errors are collected and can be accessed with
+    /// [`errors`](Self::errors).
+    pub fn lenient(mut self) -> Self {
+        self.options.lenient = true;
+        self
+    }
+
+    /// Keep reading top-level elements after the first one is closed, until
+    /// the end of the input.
+    ///
+    /// Noita itself ignores everything after the root element.
+    pub fn fragment(mut self) -> Self {
+        self.fragment = true;
+        self
+    }
+
+    /// The part of the source that was not read yet, with the leading
+    /// whitespace and comments skipped.
+    ///
+    /// After the root element was closed, this is the trailing content that
+    /// Noita ignores.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let mut reader = Reader::new("<Entity/> <!-- fine --> <Oops/>");
+    ///
+    /// reader.by_ref().for_each(drop);
+    ///
+    /// assert_eq!(reader.remainder(), "<Oops/>");
+    /// ```
+    pub fn remainder(&mut self) -> &'s str {
+        self.tokenizer.rest()
+    }
+
+    /// The whole source text.
+    pub(crate) fn source(&self) -> &'s str {
+        self.tokenizer.source()
+    }
+
+    /// Errors encountered so far in lenient mode.
+    pub fn errors(&self) -> &[NxmlError] {
+        &self.errors
+    }
+
+    /// Take the errors encountered so far in lenient mode.
+    pub fn take_errors(&mut self) -> Vec<NxmlError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// Places where Noita reads the source differently from a standard XML
+    /// parser, encountered so far.
+    ///
+    /// These are only collected if enabled with
+    /// [`ParseOptions::warnings`].
+    pub fn warnings(&self) -> &[NxmlError] {
+        &self.warnings
+    }
+
+    /// Take the warnings encountered so far.
+    pub fn take_warnings(&mut self) -> Vec<NxmlError> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    /// Give back the attribute list of a consumed [`StartTag`], so that the
+    /// next tag can reuse its allocation.
+    pub(crate) fn recycle(&mut self, mut attributes: Vec<Attribute<'s>>) {
+        attributes.clear();
+        self.spare = attributes;
+    }
+
+    /// How many elements are currently open.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Read the next event, or `None` once the root element was closed (or,
+    /// in [`fragment`](Self::fragment) mode, once the input is exhausted).
+    pub fn next_event(&mut self) -> Option<Result<Event<'s>, NxmlError>> {
+        let result = match self.state {
+            State::Start if self.fragment && self.tokenizer.rest().is_empty() => {
+                self.state = State::Done;
+                return None;
+            }
+            State::Start => self.read_root(),
+            State::Content => self.read_content(),
+            State::PendingEnd => {
+                self.state = State::Content;
+                Ok(self.end(None))
+            }
+            State::Done => return None,
+        };
+        if result.is_err() {
+            self.state = State::Done;
+        }
+        Some(result)
+    }
+
+    pub(crate) fn error(&self, err: NxmlErr, span: Span) -> NxmlError {
+        NxmlError {
+            err,
+            at: self.tokenizer.position(),
+            span: span.range(),
+            open_tag: None,
+        }
+    }
+
+    pub(crate) fn report(&mut self, error: NxmlError) -> Result<(), NxmlError> {
+        if self.options.lenient {
+            self.errors.push(error);
+            return Ok(());
+        }
+        Err(error)
+    }
+
+    fn warn(&mut self, err: NxmlErr, span: Range<usize>) {
+        self.warnings.push(NxmlError {
+            err,
+            at: self.tokenizer.position(),
+            span,
+            open_tag: None,
+        });
+    }
+
+    /// Reads the next token, checking it for quirks if warnings are enabled.
+    fn next_token(&mut self) -> (Token<'s>, Span) {
+        let (token, span) = self.tokenizer.next_token_spanned();
+        if !self.options.warnings {
+            return (token, span);
+        }
+        let raw = &self.tokenizer.source()[span.range()];
+        match token {
+            Token::Eof if raw == "\0" => self.warn(NxmlErr::NulAsEof, span.range()),
+            Token::Quoted(_) if raw.len() == 1 || !raw.ends_with('"') => {
+                self.warn(NxmlErr::UnterminatedString, span.range())
+            }
+            _ => (),
+        }
+        (token, span)
+    }
+
+    /// Checks if the text token is joined the way it is written, mirroring
+    /// [`push_text`](crate::parser::push_text).
+    fn check_text(&mut self, text: &'s str, span: Span) {
+        let source = self.tokenizer.source();
+        let Some(open) = self.stack.last_mut() else {
+            return;
+        };
+        // the first token replaces the (empty) text, the second one is
+        // joined with a space and the rest are joined with nothing
+        let separator = if open.text_tokens == 1 { " " } else { "" };
+        let gap = match open.text_tokens {
+            0 => None,
+            _ => open.text_end.map(|end| &source[end..span.start]),
+        };
+        if open.text_tokens == 1 || open.text_tokens == 0 && !text.is_empty() {
+            open.text_tokens += 1;
+        }
+        open.text_end = Some(span.end);
+
+        let written = &source[span.range()];
+        let joined = gap.is_some_and(|gap| gap != separator) || written != text;
+        if joined {
+            let start = gap.map_or(span.start, |gap| span.start - gap.len());
+            let err = NxmlErr::TextJoined {
+                read: format!("{}{text}", gap.map_or("", |_| separator)),
+                written: source[start..span.end].to_owned(),
+            };
+            self.warn(err, start..span.end);
+        }
+    }
+
+    /// Checks the value against the limit. This only makes an error, see
+    /// [`exceed`](Self::exceed) for what happens with it.
+    fn check_limit(&self, limit: Limit, value: usize, span: Span) -> Result<(), NxmlError> {
+        let max = match limit {
+            Limit::Depth => self.options.max_depth,
+            Limit::InputSize => self.options.max_input_size,
+            Limit::Attributes => self.options.max_attributes,
+            Limit::Children => self.options.max_children,
+        };
+        match max {
+            Some(max) if value > max => {
+                Err(self.error(NxmlErr::LimitExceeded { limit, max }, span))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Exceeding a limit is fatal, except in lenient mode, where the error is
+    /// recorded and the rest of the input is dropped, as if it ended right
+    /// there.
+    ///
+    /// [`ParseOptions::parse`](crate::ParseOptions::parse) still fails with
+    /// such an error, while the lenient functions return what was read.
+    fn exceed(&mut self, error: NxmlError) -> Result<(), NxmlError> {
+        self.report(error)?;
+        self.tokenizer.skip_rest();
+        Ok(())
+    }
+
+    fn end(&mut self, span: Option<Span>) -> Event<'s> {
+        let open = self.stack.pop();
+        let name = open.map_or("", |open| open.name);
+        if let (Some(open), None, true) = (open, span, self.options.warnings) {
+            // when the element is ended by a NUL, that is reported instead
+            if self.tokenizer.here().start == self.tokenizer.source().len() {
+                let err = NxmlErr::UnclosedAtEof {
+                    element: name.to_owned(),
+                };
+                self.warn(err, open.tag.range());
+            }
+        }
+        self.check_root_done();
+        Event::EndElement { name, span }
+    }
+
+    fn check_root_done(&mut self) {
+        if self.stack.is_empty() {
+            self.state = if self.fragment {
+                State::Start
+            } else {
+                State::Done
+            };
+            if self.state == State::Done && self.options.warnings {
+                let rest = self.tokenizer.rest();
+                if !rest.is_empty() {
+                    let end = self.tokenizer.source().len();
+                    self.warn(NxmlErr::TrailingContent, end - rest.len()..end);
+                }
+            }
+        }
+    }
+
+    fn read_root(&mut self) -> Result<Event<'s>, NxmlError> {
+        self.check_limit(
+            Limit::InputSize,
+            self.tokenizer.source().len(),
+            self.tokenizer.here(),
+        )?;
+
+        let (token, open) = self.next_token();
+        if !matches!(token, Token::OpenLess) {
+            self.report(self.error(NxmlErr::NoOpeningSymbolFound, open))?;
+        }
+        self.read_start_tag(open)
+    }
+
+    /// Reads the rest of an opening tag, `open` being the span of the
+    /// already consumed `<`.
+    fn read_start_tag(&mut self, open: Span) -> Result<Event<'s>, NxmlError> {
+        let mut exceeded = self.check_limit(Limit::Depth, self.stack.len() + 1, open);
+        if let Some(parent) = self.stack.last_mut() {
+            parent.children += 1;
+            parent.text_end = None;
+            let children = parent.children;
+            exceeded = exceeded.and_then(|_| self.check_limit(Limit::Children, children, open));
+        }
+        if let Err(error) = exceeded {
+            // (with no parent to close, there is nothing to return)
+            if self.stack.is_empty() {
+                return Err(error);
+            }
+            self.exceed(error)?;
+            return Ok(self.end(None));
+        }
+
+        let (name, name_span) = match self.next_token() {
+            (Token::String(name) | Token::Quoted(name), span) => (name, span),
+            (_, span) => {
+                self.report(self.error(NxmlErr::MissingElementName, open.to(span)))?;
+                ("", span)
+            }
+        };
+
+        let mut tag = StartTag {
+            name,
+            attributes: std::mem::take(&mut self.spare),
+            span: open.to(name_span),
+            name_span,
+        };
+
+        loop {
+            let (token, span) = self.next_token();
+            if !matches!(token, Token::Eof) {
+                tag.span = tag.span.to(span);
+            }
+            match token {
+                Token::Eof => {
+                    self.push(&tag);
+                    self.state = State::PendingEnd;
+                    return Ok(Event::StartElement(tag));
+                }
+                Token::Slash => {
+                    if self.tokenizer.take_byte(b'>') {
+                        tag.span = tag.span.to(self.tokenizer.span_from(span.end));
+                        self.check_root_done();
+                        return Ok(Event::SelfClose(tag));
+                    }
+                    if self.options.warnings {
+                        let err = NxmlErr::SlashAsClose {
+                            tag: name.to_owned(),
+                        };
+                        self.warn(err, span.range());
+                    }
+                    break;
+                }
+                Token::CloseGreater => break,
+                Token::String(key) | Token::Quoted(key) => {
+                    let (token, equal_span) = self.next_token();
+                    let Token::Equal = token else {
+                        if !matches!(token, Token::Eof) {
+                            tag.span = tag.span.to(equal_span);
+                        }
+                        let err = NxmlErr::MissingEqualsSign {
+                            tag: name.to_owned(),
+                            attribute: key.to_owned(),
+                        };
+                        self.report(self.error(err, span))?;
+                        continue;
+                    };
+                    tag.span = tag.span.to(equal_span);
+
+                    let (token, value_span) = self.next_token();
+                    if !matches!(token, Token::Eof) {
+                        tag.span = tag.span.to(value_span);
+                    }
+                    let Some(value) = token.string() else {
+                        let err = NxmlErr::MissingAttributeValue {
+                            tag: name.to_owned(),
+                            attribute: key.to_owned(),
+                        };
+                        self.report(self.error(err, span.to(equal_span)))?;
+                        continue;
+                    };
+
+                    let count = tag.attributes.len() + 1;
+                    if let Err(error) =
+                        self.check_limit(Limit::Attributes, count, span.to(value_span))
+                    {
+                        self.exceed(error)?;
+                        self.push(&tag);
+                        self.state = State::PendingEnd;
+                        return Ok(Event::StartElement(tag));
+                    }
+                    tag.attributes.push(Attribute {
+                        key,
+                        value,
+                        key_span: span,
+                        value_span,
+                    });
+                }
+                _ => (),
+            }
+        }
+
+        self.push(&tag);
+        self.state = State::Content;
+        Ok(Event::StartElement(tag))
+    }
+
+    fn push(&mut self, tag: &StartTag<'s>) {
+        self.stack.push(Open {
+            name: tag.name,
+            tag: tag.span,
+            children: 0,
+            text_tokens: 0,
+            text_end: None,
+        });
+    }
+
+    fn read_content(&mut self) -> Result<Event<'s>, NxmlError> {
+        let (token, open) = self.next_token();
+        match token {
+            Token::Eof => return Ok(self.end(None)),
+            Token::OpenLess => (),
+            token => {
+                let text = token.as_str();
+                if self.options.warnings {
+                    self.check_text(text, open);
+                }
+                return Ok(Event::Text { text, span: open });
+            }
+        }
+
+        if !self.tokenizer.take_byte(b'/') {
+            return self.read_start_tag(open);
+        }
+
+        let (current, open_tag) = match self.stack.last() {
+            Some(current) => (current.name, Some(current.tag.range())),
+            None => ("", None),
+        };
+        let err = match self.next_token().0 {
+            Token::String(name) | Token::Quoted(name) if name == current => {
+                if let Token::CloseGreater = self.next_token().0 {
+                    let span = self.tokenizer.span_from(open.start);
+                    return Ok(self.end(Some(span)));
+                }
+                NxmlErr::NoClosingSymbolFound {
+                    element: name.to_owned(),
+                }
+            }
+            token => NxmlErr::MismatchedClosingTag {
+                expected: current.to_owned(),
+                got: token.as_str().to_owned(),
+            },
+        };
+        let span = self.tokenizer.span_from(open.start);
+        self.report(NxmlError {
+            open_tag,
+            ..self.error(err, span)
+        })?;
+        Ok(self.end(Some(span)))
+    }
+}
+
+impl<'s> Iterator for Reader<'s> {
+    type Item = Result<Event<'s>, NxmlError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_event()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(s: &str) -> Vec<String> {
+        events_of(Reader::new(s))
+    }
+
+    fn events_of(reader: Reader) -> Vec<String> {
+        reader
+            .map(|e| match e.unwrap() {
+                Event::StartElement(tag) => format!("<{}>", tag.name),
+                Event::SelfClose(tag) => format!("<{}/>", tag.name),
+                Event::Text { text, .. } => text.to_owned(),
+                Event::EndElement { name, span: None } => format!("</{name}?>"),
+                Event::EndElement { name, .. } => format!("</{name}>"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn stops_after_root() {
+        assert_eq!(
+            events("<a>hi <b/><c x=\"y\">there</c></a><ignored/>"),
+            ["<a>", "hi", "<b/>", "<c>", "there", "</c>", "</a>"]
+        );
+    }
+
+    #[test]
+    fn fragment_reads_all_roots() {
+        assert_eq!(
+            events_of(Reader::new(" <a/> <!-- --> <b>x</b>\n").fragment()),
+            ["<a/>", "<b>", "x", "</b>"]
+        );
+    }
+
+    #[test]
+    fn closes_everything_at_eof() {
+        assert_eq!(
+            events("<a><b><c"),
+            ["<a>", "<b>", "<c>", "</c?>", "</b?>", "</a?>"]
+        );
+    }
+
+    #[test]
+    fn nul_closes_only_current_element() {
+        assert_eq!(
+            events("<a><b \0/><c/></a>"),
+            ["<a>", "<b>", "</b?>", "/", ">", "<c/>", "</a>"]
+        );
+    }
+
+    #[test]
+    fn warns_about_quirks() {
+        let source = "<a>\"x\" <b \0/><c/ > one two</c><d x=\"y></d></a> <e/>";
+        let mut reader = Reader::with_options(source, &ParseOptions::new().warnings());
+
+        reader.by_ref().for_each(|e| drop(e.unwrap()));
+
+        let warnings = reader
+            .take_warnings()
+            .into_iter()
+            .map(|w| (w.err.code(), &source[w.span]))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            warnings,
+            [
+                ("NXML0008", "\"x\""),
+                ("NXML0010", "\0"),
+                ("NXML0012", "/"),
+                ("NXML0008", " two"),
+                ("NXML0009", "\"y></d></a> <e/>"),
+                ("NXML0011", "<d x=\"y></d></a> <e/>"),
+                ("NXML0011", "<a>"),
+            ]
+        );
+
+        let source = "<a/> <!-- --> <b/>";
+        let mut reader = Reader::with_options(source, &ParseOptions::new().warnings());
+
+        reader.by_ref().for_each(drop);
+
+        let [warning] = reader.warnings() else {
+            panic!("expected a single warning");
+        };
+        assert_eq!(warning.err.code(), "NXML0013");
+        assert_eq!(&source[warning.span.clone()], "<b/>");
+    }
+}
@@ -0,0 +1,636 @@
+use std::{borrow::Cow, iter::Enumerate, slice, str::FromStr};
+
+use thiserror::Error;
+
+use crate::{
+    accessor::ElementAccessor,
+    element::{GenericElement, NxmlStr},
+};
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum SelectorErr {
+    #[error("Expected {expected}")]
+    Expected { expected: &'static str },
+    #[error("Unknown pseudo-class ':{name}'")]
+    UnknownPseudoClass { name: String },
+    #[error("Quoted string is not terminated")]
+    UnterminatedString,
+    #[error("Expected a positive number")]
+    InvalidNumber,
+}
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[error("{err} at offset {at}")]
+pub struct SelectorError {
+    pub err: SelectorErr,
+    /// The byte offset in the selector where the error is.
+    pub at: usize,
+}
+
+/// A parsed selector, for finding elements with
+/// [`select`](GenericElement::select).
+///
+/// The syntax is a subset of CSS selectors, with a bit of XPath:
+/// - `Name` matches elements by name, `*` matches any element;
+/// - `[key]` matches elements that have the attribute, and `[key=value]`,
+///   `[key^=prefix]`, `[key$=suffix]`, `[key*=part]` match by its value. The
+///   value is either a name or a quoted string;
+/// - `[key~=word]` matches if the value is a list of words (separated by commas
+///   or whitespace, like the `_tags` of an entity) with the given one among
+///   them;
+/// - `:nth-child(n)`, `:first-child` and `:last-child` match by the position of
+///   the element among its siblings, counting from one;
+/// - `A B` or `A/**/B` matches `B` anywhere below `A`, `A > B` or `A/B` matches
+///   `B` that is a direct child of `A`;
+/// - `A, B` matches either of the selectors.
+///
+/// The parts of a name-and-filters group can be combined, e.g.
+/// `LuaComponent[script_source_file$=".lua"]:first-child`, and a leading
+/// `**/` is allowed and does nothing, as the selectors match at any depth.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let selector: Selector = "Entity > LuaComponent[script_source_file$='.lua']".parse().unwrap();
+///
+/// let element = nxml_ref!(<Entity>
+///     <LuaComponent script_source_file="a.lua"/>
+///     <LuaComponent script_source_file="b.txt"/>
+/// </Entity>);
+///
+/// assert_eq!(selector.select(&element).count(), 1);
+///
+/// let error = Selector::parse("Entity > [a").unwrap_err();
+/// assert_eq!(error.to_string(), "Expected ']' at offset 11");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Selector {
+    alternatives: Vec<Complex>,
+}
+
+/// A chain of compounds, each one with the combinator that links it to the
+/// previous one (which is ignored for the first).
+type Complex = Vec<(Combinator, Compound)>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Combinator {
+    Child,
+    Descendant,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Compound {
+    name: Option<String>,
+    filters: Vec<Filter>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Filter {
+    Attr(String),
+    AttrValue(String, AttrOp, String),
+    NthChild(usize),
+    LastChild,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttrOp {
+    Equals,
+    Word,
+    Prefix,
+    Suffix,
+    Contains,
+}
+
+impl Selector {
+    /// Parse a selector.
+    pub fn parse(selector: &str) -> Result<Self, SelectorError> {
+        SelectorParser {
+            s: selector,
+            pos: 0,
+        }
+        .parse()
+    }
+
+    /// Iterate over the elements below the given one that match the
+    /// selector, in the order their opening tags appear in the source.
+    ///
+    /// The element itself is never yielded, but it takes part in the
+    /// matching, so e.g. `Entity > LuaComponent` finds the components of a
+    /// root `Entity`.
+    pub fn select<'a, S: NxmlStr>(&'a self, element: &'a GenericElement<S>) -> Select<'a, S> {
+        Select::new(Cow::Borrowed(self), element)
+    }
+
+    /// Mutable version of [`select`](Self::select).
+    ///
+    /// As the elements are all borrowed mutably at once, the ones below a
+    /// matched element are not looked at.
+    pub fn select_mut<'a, S: NxmlStr>(
+        &self,
+        element: &'a mut GenericElement<S>,
+    ) -> SelectMut<'a, S> {
+        let mut select = self.select(element);
+        let mut paths = Vec::new();
+        while select.next().is_some() {
+            paths.push(select.path[1..].iter().map(|s| s.index).collect::<Vec<_>>());
+            select.skip_children();
+        }
+
+        let mut found = Vec::with_capacity(paths.len());
+        collect_mut(element, &paths, 0, &mut found);
+        SelectMut(found.into_iter())
+    }
+
+    fn matches<E: ElementAccessor>(&self, path: &[Step<'_, E>]) -> bool {
+        self.alternatives
+            .iter()
+            .any(|complex| matches_complex(complex, path))
+    }
+}
+
+impl FromStr for Selector {
+    type Err = SelectorError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl<S: NxmlStr> GenericElement<S> {
+    /// Iterate over the elements below this one that match the selector,
+    /// see [`Selector`] for the syntax.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = nxml_ref!(<Entity>
+    ///     <Entity _tags="enemy,enabled_in_world"><DamageModelComponent hp="4"/></Entity>
+    ///     <Entity _tags="enemy"><DamageModelComponent hp="2"/></Entity>
+    /// </Entity>);
+    ///
+    /// let hp = element
+    ///     .select("**/DamageModelComponent")
+    ///     .unwrap()
+    ///     .map(|e| e % "hp")
+    ///     .collect::<Vec<_>>();
+    /// assert_eq!(hp, ["4", "2"]);
+    ///
+    /// let enabled = element.select("[_tags~=enabled_in_world] > *").unwrap();
+    /// assert_eq!(enabled.map(|e| e % "hp").collect::<Vec<_>>(), ["4"]);
+    ///
+    /// let second = element.select("Entity:nth-child(2)").unwrap();
+    /// assert_eq!(second.map(|e| e % "_tags").collect::<Vec<_>>(), ["enemy"]);
+    /// ```
+    pub fn select(&self, selector: &str) -> Result<Select<'_, S>, SelectorError> {
+        Ok(Select::new(Cow::Owned(Selector::parse(selector)?), self))
+    }
+
+    /// Mutable version of [`select`](Self::select).
+    ///
+    /// As the elements are all borrowed mutably at once, the ones below a
+    /// matched element are not looked at.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let mut element = nxml!(<Entity>
+    ///     <LuaComponent script_source_file="a.lua"/>
+    ///     <Entity><LuaComponent script_source_file="b.lua"/></Entity>
+    /// </Entity>);
+    ///
+    /// for lua in element.select_mut("LuaComponent").unwrap() {
+    ///     lua.set_attr("execute_every_n_frame", 60);
+    /// }
+    ///
+    /// assert_eq!(&element / "Entity" / "LuaComponent" % "execute_every_n_frame", "60");
+    /// ```
+    pub fn select_mut(&mut self, selector: &str) -> Result<SelectMut<'_, S>, SelectorError> {
+        Ok(Selector::parse(selector)?.select_mut(self))
+    }
+}
+
+/// An element on the path from the element a selection started at.
+#[derive(Debug)]
+struct Step<'a, E> {
+    element: &'a E,
+    /// The index among the siblings.
+    index: usize,
+    /// The number of siblings, including the element itself.
+    count: usize,
+}
+
+/// An iterator over the elements that match a [`Selector`], created by
+/// [`select`](GenericElement::select).
+#[derive(Debug)]
+pub struct Select<'a, S: NxmlStr> {
+    selector: Cow<'a, Selector>,
+    path: Vec<Step<'a, GenericElement<S>>>,
+    children: Vec<Enumerate<slice::Iter<'a, GenericElement<S>>>>,
+}
+
+impl<'a, S: NxmlStr> Select<'a, S> {
+    fn new(selector: Cow<'a, Selector>, element: &'a GenericElement<S>) -> Self {
+        Self {
+            selector,
+            path: vec![Step {
+                element,
+                index: 0,
+                count: 1,
+            }],
+            children: vec![element.children.iter().enumerate()],
+        }
+    }
+
+    fn skip_children(&mut self) {
+        self.path.pop();
+        self.children.pop();
+    }
+}
+
+impl<'a, S: NxmlStr> Iterator for Select<'a, S> {
+    type Item = &'a GenericElement<S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(children) = self.children.last_mut() {
+            let Some((index, element)) = children.next() else {
+                self.path.pop();
+                self.children.pop();
+                continue;
+            };
+            let count = self.path.last().map_or(1, |s| s.element.children.len());
+            self.path.push(Step {
+                element,
+                index,
+                count,
+            });
+            self.children.push(element.children.iter().enumerate());
+            if self.selector.matches(&self.path) {
+                return Some(element);
+            }
+        }
+        None
+    }
+}
+
+/// A mutable iterator over the elements that match a [`Selector`], created
+/// by [`select_mut`](GenericElement::select_mut).
+#[derive(Debug)]
+pub struct SelectMut<'a, S: NxmlStr>(std::vec::IntoIter<&'a mut GenericElement<S>>);
+
+impl<'a, S: NxmlStr> Iterator for SelectMut<'a, S> {
+    type Item = &'a mut GenericElement<S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl<S: NxmlStr> ExactSizeIterator for SelectMut<'_, S> {}
+
+impl<S: NxmlStr> DoubleEndedIterator for SelectMut<'_, S> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.next_back()
+    }
+}
+
+/// Collect the elements at the given paths of child indices, which are in
+/// the source order and none of which is a prefix of another.
+fn collect_mut<'a, S: NxmlStr>(
+    element: &'a mut GenericElement<S>,
+    mut paths: &[Vec<usize>],
+    depth: usize,
+    found: &mut Vec<&'a mut GenericElement<S>>,
+) {
+    for (index, child) in element.children.iter_mut().enumerate() {
+        let n = paths.iter().take_while(|p| p[depth] == index).count();
+        let (here, rest) = paths.split_at(n);
+        paths = rest;
+        match here {
+            [] => {}
+            [path] if path.len() == depth + 1 => found.push(child),
+            _ => collect_mut(child, here, depth + 1, found),
+        }
+    }
+}
+
+fn matches_complex<E: ElementAccessor>(
+    complex: &[(Combinator, Compound)],
+    path: &[Step<'_, E>],
+) -> bool {
+    let (Some(((combinator, compound), complex)), Some((step, path))) =
+        (complex.split_last(), path.split_last())
+    else {
+        return false;
+    };
+    if !compound.matches(step) {
+        return false;
+    }
+    if complex.is_empty() {
+        return true;
+    }
+    match combinator {
+        Combinator::Child => matches_complex(complex, path),
+        Combinator::Descendant => (1..=path.len())
+            .rev()
+            .any(|end| matches_complex(complex, &path[..end])),
+    }
+}
+
+impl Compound {
+    fn matches<E: ElementAccessor>(&self, step: &Step<'_, E>) -> bool {
+        let element = step.element;
+        if let Some(name) = &self.name {
+            if element.name() != name {
+                return false;
+            }
+        }
+        self.filters.iter().all(|filter| match filter {
+            Filter::Attr(key) => element.attr(key).is_some(),
+            Filter::AttrValue(key, op, expected) => element
+                .attr(key)
+                .is_some_and(|value| op.matches(value, expected)),
+            Filter::NthChild(n) => step.index + 1 == *n,
+            Filter::LastChild => step.index + 1 == step.count,
+        })
+    }
+}
+
+impl AttrOp {
+    fn matches(self, value: &str, expected: &str) -> bool {
+        match self {
+            AttrOp::Equals => value == expected,
+            AttrOp::Word => value
+                .split(|c: char| c == ',' || c.is_whitespace())
+                .any(|word| word == expected),
+            AttrOp::Prefix => value.starts_with(expected),
+            AttrOp::Suffix => value.ends_with(expected),
+            AttrOp::Contains => value.contains(expected),
+        }
+    }
+}
+
+struct SelectorParser<'a> {
+    s: &'a str,
+    pos: usize,
+}
+
+impl SelectorParser<'_> {
+    fn parse(mut self) -> Result<Selector, SelectorError> {
+        let mut alternatives = vec![self.complex()?];
+        while self.eat(",") {
+            alternatives.push(self.complex()?);
+        }
+        Ok(Selector { alternatives })
+    }
+
+    fn error<T>(&self, err: SelectorErr) -> Result<T, SelectorError> {
+        Err(SelectorError { err, at: self.pos })
+    }
+
+    fn expected<T>(&self, expected: &'static str) -> Result<T, SelectorError> {
+        self.error(SelectorErr::Expected { expected })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.s[self.pos..].chars().next()
+    }
+
+    fn eat(&mut self, prefix: &str) -> bool {
+        if self.s[self.pos..].starts_with(prefix) {
+            self.pos += prefix.len();
+            return true;
+        }
+        false
+    }
+
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.pos;
+        while let Some(c) = self.peek().filter(|c| c.is_whitespace()) {
+            self.pos += c.len_utf8();
+        }
+        self.pos > start
+    }
+
+    fn complex(&mut self) -> Result<Complex, SelectorError> {
+        let mut complex = Vec::new();
+        let mut combinator = Combinator::Descendant;
+        loop {
+            self.skip_whitespace();
+            if self.eat("**") {
+                self.skip_whitespace();
+                if !self.eat("/") {
+                    return self.expected("'/' after '**'");
+                }
+                combinator = Combinator::Descendant;
+                continue;
+            }
+            complex.push((combinator, self.compound()?));
+
+            let whitespace = self.skip_whitespace();
+            combinator = match self.peek() {
+                None | Some(',') => return Ok(complex),
+                Some('>' | '/') => {
+                    self.pos += 1;
+                    Combinator::Child
+                }
+                _ if whitespace => Combinator::Descendant,
+                _ => return self.expected("a combinator"),
+            };
+        }
+    }
+
+    fn compound(&mut self) -> Result<Compound, SelectorError> {
+        let any_name = self.eat("*");
+        let mut compound = Compound {
+            name: if any_name {
+                None
+            } else {
+                self.name().map(String::from)
+            },
+            filters: Vec::new(),
+        };
+        let mut any = any_name || compound.name.is_some();
+        loop {
+            if self.eat("[") {
+                compound.filters.push(self.attribute()?);
+            } else if self.eat(":") {
+                compound.filters.push(self.pseudo_class()?);
+            } else if any {
+                return Ok(compound);
+            } else {
+                return self.expected("an element name, '*', '[' or ':'");
+            }
+            any = true;
+        }
+    }
+
+    fn name(&mut self) -> Option<&str> {
+        let rest = &self.s[self.pos..];
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.')))
+            .unwrap_or(rest.len());
+        if len == 0 {
+            return None;
+        }
+        self.pos += len;
+        Some(&rest[..len])
+    }
+
+    fn attribute(&mut self) -> Result<Filter, SelectorError> {
+        self.skip_whitespace();
+        let Some(key) = self.name().map(String::from) else {
+            return self.expected("an attribute name");
+        };
+        self.skip_whitespace();
+        if self.eat("]") {
+            return Ok(Filter::Attr(key));
+        }
+        let op = [
+            ("=", AttrOp::Equals),
+            ("~=", AttrOp::Word),
+            ("^=", AttrOp::Prefix),
+            ("$=", AttrOp::Suffix),
+            ("*=", AttrOp::Contains),
+        ]
+        .into_iter()
+        .find_map(|(s, op)| self.eat(s).then_some(op));
+        let Some(op) = op else {
+            return self.expected("']'");
+        };
+        self.skip_whitespace();
+        let value = self.value()?;
+        self.skip_whitespace();
+        if !self.eat("]") {
+            return self.expected("']'");
+        }
+        Ok(Filter::AttrValue(key, op, value))
+    }
+
+    fn value(&mut self) -> Result<String, SelectorError> {
+        let Some(quote @ ('"' | '\'')) = self.peek() else {
+            return match self.name() {
+                Some(value) => Ok(value.into()),
+                None => self.expected("an attribute value"),
+            };
+        };
+        let rest = &self.s[self.pos + 1..];
+        let Some(len) = rest.find(quote) else {
+            return self.error(SelectorErr::UnterminatedString);
+        };
+        self.pos += len + 2;
+        Ok(rest[..len].into())
+    }
+
+    fn pseudo_class(&mut self) -> Result<Filter, SelectorError> {
+        let start = self.pos;
+        match self.name() {
+            Some("first-child") => Ok(Filter::NthChild(1)),
+            Some("last-child") => Ok(Filter::LastChild),
+            Some("nth-child") => {
+                if !self.eat("(") {
+                    return self.expected("'('");
+                }
+                self.skip_whitespace();
+                let start = self.pos;
+                let n = match self.name().map(str::parse) {
+                    Some(Ok(n)) if n > 0 => n,
+                    _ => {
+                        self.pos = start;
+                        return self.error(SelectorErr::InvalidNumber);
+                    }
+                };
+                self.skip_whitespace();
+                if !self.eat(")") {
+                    return self.expected("')'");
+                }
+                Ok(Filter::NthChild(n))
+            }
+            name => {
+                let name = name.unwrap_or_default().into();
+                self.pos = start;
+                self.error(SelectorErr::UnknownPseudoClass { name })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+
+    fn names(source: &str, selector: &str) -> Vec<String> {
+        parse(source)
+            .unwrap()
+            .select(selector)
+            .unwrap()
+            .map(|e| match e.attr("id") {
+                Some(id) => format!("{}#{id}", e.name),
+                None => e.name.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn combinators() {
+        let source = r#"<a><b id="1"><c id="2"><b id="3"/></c></b><c id="4"/></a>"#;
+
+        assert_eq!(names(source, "b"), ["b#1", "b#3"]);
+        assert_eq!(names(source, "a > b"), ["b#1"]);
+        assert_eq!(names(source, "a/b"), ["b#1"]);
+        assert_eq!(names(source, "a b"), ["b#1", "b#3"]);
+        assert_eq!(names(source, "a/**/b"), ["b#1", "b#3"]);
+        assert_eq!(names(source, "**/c"), ["c#2", "c#4"]);
+        assert_eq!(names(source, "b c > b"), ["b#3"]);
+        assert_eq!(names(source, "a > *:last-child, c b"), ["b#3", "c#4"]);
+        assert_eq!(names(source, "*:nth-child(1)"), ["b#1", "c#2", "b#3"]);
+        assert_eq!(names(source, "[id^=3], [id='4']"), ["b#3", "c#4"]);
+        assert!(names(source, "a").is_empty());
+    }
+
+    #[test]
+    fn select_mut_skips_nested_matches() {
+        let mut element = parse(r#"<a><b><b/></b><c><b/></c></a>"#).unwrap();
+
+        let found = element.select_mut("b").unwrap();
+        assert_eq!(found.len(), 2);
+        for b in found {
+            b.children.clear();
+            b.set_attr("x", "y");
+        }
+
+        assert_eq!(element.to_string(), r#"<a><b x="y"/><c><b x="y"/></c></a>"#);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |s| Selector::parse(s).unwrap_err().to_string();
+
+        assert_eq!(
+            error(""),
+            "Expected an element name, '*', '[' or ':' at offset 0"
+        );
+        assert_eq!(
+            error("a >"),
+            "Expected an element name, '*', '[' or ':' at offset 3"
+        );
+        assert_eq!(
+            error("a[b=\"c]"),
+            "Quoted string is not terminated at offset 4"
+        );
+        assert_eq!(
+            error("a:nth-child(0)"),
+            "Expected a positive number at offset 12"
+        );
+        assert_eq!(
+            error("a:hover"),
+            "Unknown pseudo-class ':hover' at offset 2"
+        );
+        assert_eq!(error("**a"), "Expected '/' after '**' at offset 2");
+        assert_eq!(error("a[b!=c]"), "Expected ']' at offset 3");
+    }
+}
@@ -1,16 +1,59 @@
-use std::fmt::Display;
+//! The lexer that the parser is built on.
+//!
+//! It splits the source into [`Token`]s the same way Noita does, skipping
+//! whitespace, comments, declarations and processing instructions between
+//! them. Those can be read with [`Tokenizer::next_trivia`] instead, e.g. for
+//! syntax highlighting.
+//!
+//! # Example
+//! ```rust
+//! # use nxml_rs::tokenizer::*;
+//! let source = r#"<Entity name="player"><!-- hi --></Entity>"#;
+//!
+//! let tokens = Tokenizer::new(source)
+//!     .map(|(token, span)| format!("{token:?} {:?}", &source[span.range()]))
+//!     .collect::<Vec<_>>();
+//!
+//! assert_eq!(tokens, [
+//!     r#"OpenLess "<""#,
+//!     r#"String("Entity") "Entity""#,
+//!     r#"String("name") "name""#,
+//!     r#"Equal "=""#,
+//!     r#"Quoted("player") "\"player\"""#,
+//!     r#"CloseGreater ">""#,
+//!     r#"OpenLess "<""#,
+//!     r#"Slash "/""#,
+//!     r#"String("Entity") "Entity""#,
+//!     r#"CloseGreater ">""#,
+//! ]);
+//! ```
 
-#[derive(Debug)]
+use std::{cell::Cell, fmt::Display};
+
+/// A single token of the source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Token<'s> {
+    /// The end of the input, or a NUL character, which Noita treats the same.
     Eof,
+    /// `<`
     OpenLess,
+    /// `>`
     CloseGreater,
+    /// `/`
     Slash,
+    /// `=`
     Equal,
+    /// A run of anything other than whitespace and the punctuation above.
     String(&'s str),
+    /// A string in double quotes, without the quotes.
+    ///
+    /// If the closing quote is missing, this runs to the end of the input.
+    /// Noita treats it the same as [`Token::String`].
+    Quoted(&'s str),
 }
 
 impl<'s> Token<'s> {
+    /// The text of the token, as Noita sees it.
     pub fn as_str(&self) -> &'s str {
         match self {
             Token::Eof => "",
@@ -18,172 +61,477 @@ impl<'s> Token<'s> {
             Token::CloseGreater => ">",
             Token::Slash => "/",
             Token::Equal => "=",
-            Token::String(s) => s,
+            Token::String(s) | Token::Quoted(s) => s,
+        }
+    }
+
+    /// The value of a [`Token::String`] or a [`Token::Quoted`].
+    pub fn string(&self) -> Option<&'s str> {
+        match self {
+            Token::String(s) | Token::Quoted(s) => Some(s),
+            _ => None,
         }
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// A line and column in the source text, both 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Position {
+    /// The line, counting the `\n` characters.
     pub line: usize,
+    /// The column, counted in characters.
     pub column: usize,
 }
 
+impl Position {
+    /// The line and column of the given byte offset in the source.
+    ///
+    /// The column is counted in characters.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let source = "<Entity>\n\t<Sprite/>\n</Entity>";
+    ///
+    /// assert_eq!(Position::at(source, 10), Position { line: 2, column: 2 });
+    /// ```
+    pub fn at(source: &str, offset: usize) -> Position {
+        Position::START.advance(source, 0, offset)
+    }
+
+    const START: Position = Position { line: 1, column: 1 };
+
+    /// Moves the position from one byte offset to a later one.
+    fn advance(self, source: &str, from: usize, to: usize) -> Position {
+        let skipped = &source.as_bytes()[from..to];
+        match memchr::memrchr(b'\n', skipped) {
+            Some(i) => Position {
+                line: self.line + memchr::memchr_iter(b'\n', skipped).count(),
+                column: 1 + source[from + i + 1..to].chars().count(),
+            },
+            None => Position {
+                line: self.line,
+                column: self.column + source[from..to].chars().count(),
+            },
+        }
+    }
+}
+
 impl Display for Position {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(f, "{}:{}", self.line, self.column)
     }
 }
 
+/// A region of the source text, as a byte range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Span {
+    /// Byte offset of the start of the region.
+    pub start: usize,
+    /// Byte offset right after the end of the region.
+    pub end: usize,
+}
+
+impl Span {
+    /// The byte range of this span, usable for indexing the source string.
+    pub fn range(&self) -> std::ops::Range<usize> {
+        self.start..self.end
+    }
+
+    /// A span that covers both this span and the other one.
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            start: self.start,
+            end: other.end,
+        }
+    }
+
+    /// The line and column of the start of the span, see [`Position::at`].
+    pub fn start_pos(&self, source: &str) -> Position {
+        Position::at(source, self.start)
+    }
+
+    /// The line and column of the end of the span, see [`Position::at`].
+    pub fn end_pos(&self, source: &str) -> Position {
+        Position::at(source, self.end)
+    }
+}
+
+/// Kinds of input that the tokenizer skips between tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trivia {
+    /// A run of spaces, tabs and line breaks.
+    Whitespace,
+    /// `<!-- ... -->`
+    Comment,
+    /// `<!...>`, e.g. `<!DOCTYPE ...>`
+    Declaration,
+    /// `<?...?>`, e.g. `<?xml version="1.0"?>`
+    ProcessingInstruction,
+}
+
+/// How the tokenizer reached the end of the input, which affects the
+/// reported position there.
+#[derive(Debug, Clone, Copy)]
+enum End {
+    NotReached,
+    /// The last character was skipped, which does not advance the position.
+    Skipped,
+    /// A delimiter (or a prefix of it) starting at the offset was taken,
+    /// which advances the column by the whole delimiter.
+    Taken {
+        at: usize,
+        columns: usize,
+    },
+}
+
+/// A lexer over a source text.
+///
+/// Iterating over it produces every token along with its span, until the
+/// end of the input - note that a NUL character produces a [`Token::Eof`]
+/// without ending the iteration.
 #[derive(Debug)]
 pub struct Tokenizer<'s> {
     data: &'s str,
     current_index: usize,
-    position: Position,
+    end: End,
+    /// The last computed position, to not count the lines from the start of
+    /// the source every time.
+    cache: Cell<(usize, Position)>,
 }
 
 impl<'s> Tokenizer<'s> {
-    pub fn new(data: &str) -> Tokenizer {
+    /// Create a tokenizer at the start of the source.
+    pub fn new(data: &str) -> Tokenizer<'_> {
         Tokenizer {
             data,
             current_index: 0,
-            position: Position { line: 1, column: 1 },
+            end: End::NotReached,
+            cache: Cell::new((0, Position::START)),
         }
     }
 
+    /// The whole source text.
+    pub fn source(&self) -> &'s str {
+        self.data
+    }
+
+    /// The line and column of the current position.
+    ///
+    /// This is computed on demand, so it should not be called for every
+    /// token.
+    ///
+    /// At the end of the input this is the position of the last character
+    /// (unless the input ended with a delimiter of something), which is what
+    /// the errors have always reported.
     pub fn position(&self) -> Position {
-        self.position
+        match self.end {
+            End::NotReached => self.position_at(self.current_index),
+            End::Skipped => {
+                let last = self.data.char_indices().next_back().map_or(0, |(i, _)| i);
+                self.position_at(last)
+            }
+            End::Taken { at, columns } => {
+                let position = self.position_at(at);
+                Position {
+                    column: position.column + columns,
+                    ..position
+                }
+            }
+        }
+    }
+
+    fn position_at(&self, offset: usize) -> Position {
+        let (cached, position) = match self.cache.get() {
+            (cached, position) if cached <= offset => (cached, position),
+            _ => (0, Position::START),
+        };
+        let position = position.advance(self.data, cached, offset);
+        self.cache.set((offset, position));
+        position
+    }
+
+    /// An empty span at the current position.
+    pub fn here(&self) -> Span {
+        self.span_from(self.current_index)
+    }
+
+    /// A span from the given start to the current position.
+    pub fn span_from(&self, start: usize) -> Span {
+        Span {
+            start,
+            end: self.current_index,
+        }
+    }
+
+    /// The rest of the source text, after skipping any whitespace and
+    /// comments.
+    pub fn rest(&mut self) -> &'s str {
+        self.skip_whitespace();
+        &self.data[self.current_index..]
     }
 
     fn eof(&self) -> bool {
         self.current_index >= self.data.len()
     }
 
-    fn cur(&self) -> char {
+    fn cur(&self) -> u8 {
         self.data
-            .get(self.current_index..)
-            .and_then(|s| s.chars().next())
+            .as_bytes()
+            .get(self.current_index)
+            .copied()
             .unwrap_or_default()
     }
 
-    fn skip(&mut self) {
-        let ch = self.cur();
-        self.current_index += ch.len_utf8();
-        if self.current_index >= self.data.len() {
+    /// Moves to the given offset, which is at or past the current one.
+    fn skip_to(&mut self, index: usize) {
+        if index >= self.data.len() {
             self.current_index = self.data.len();
-            return;
-        }
-        if ch == '\n' {
-            self.position.line += 1;
-            self.position.column = 1;
+            self.end = End::Skipped;
         } else {
-            self.position.column += 1;
+            self.current_index = index;
         }
     }
 
-    fn peek_string(&self, s: &str) -> bool {
-        self.data
-            .get(self.current_index..)
-            .unwrap_or_default()
-            .chars()
-            .zip(s.chars())
-            .all(|(a, b)| a == b)
+    /// Skips the rest of the input, as if it ended at the current position.
+    pub(crate) fn skip_rest(&mut self) {
+        self.skip_to(self.data.len());
     }
 
-    fn take_string(&mut self, s: &str) -> bool {
-        let peeked = self.peek_string(s);
-        if !peeked {
-            return false;
+    /// Skips a whole character.
+    fn skip(&mut self) {
+        let len = match self.cur() {
+            0x00..=0x7F => 1,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            _ => 4,
+        };
+        self.skip_to(self.current_index + len);
+    }
+
+    /// Skips while the bytes match the predicate, which must only stop at
+    /// ASCII bytes, so that it does not stop in the middle of a character.
+    fn skip_while(&mut self, predicate: impl Fn(u8) -> bool) {
+        let rest = &self.data.as_bytes()[self.current_index..];
+        let len = rest.iter().position(|&b| !predicate(b));
+        self.skip_to(self.current_index + len.unwrap_or(rest.len()));
+    }
+
+    /// Takes the string if the input continues with it, or if the input ends
+    /// with a prefix of it.
+    fn take_string(&mut self, s: &'static str) -> bool {
+        let rest = &self.data.as_bytes()[self.current_index..];
+        if rest.starts_with(s.as_bytes()) {
+            self.take_at(self.current_index, s);
+            return true;
+        }
+        if s.as_bytes().starts_with(rest) {
+            self.end = End::Taken {
+                at: self.current_index,
+                columns: s.len(),
+            };
+            self.current_index = self.data.len();
+            return true;
+        }
+        false
+    }
+
+    /// Moves past the (ASCII) string that is at the given offset.
+    fn take_at(&mut self, at: usize, s: &'static str) {
+        self.current_index = at + s.len();
+        if self.eof() {
+            self.end = End::Taken {
+                at,
+                columns: s.len(),
+            };
         }
-        self.current_index += s.len();
-        for ch in s.chars() {
-            if ch != '\n' {
-                self.position.column += 1;
-                continue;
+    }
+
+    /// Skips everything up to and including the (ASCII) delimiter.
+    fn skip_past(&mut self, delimiter: &'static str) {
+        let rest = &self.data.as_bytes()[self.current_index..];
+        let found = match delimiter.as_bytes() {
+            [byte] => memchr::memchr(*byte, rest),
+            bytes => memchr::memmem::find(rest, bytes),
+        };
+        if let Some(i) = found {
+            self.take_at(self.current_index + i, delimiter);
+            return;
+        }
+        // the input might end with a prefix of the delimiter, which is
+        // taken as if it was the whole of it
+        let tail = rest.len().saturating_sub(delimiter.len() - 1);
+        let prefix = (tail..rest.len()).find(|&i| delimiter.as_bytes().starts_with(&rest[i..]));
+        match prefix {
+            Some(i) => {
+                self.current_index += i;
+                self.take_string(delimiter);
             }
-            self.position.line += 1;
-            self.position.column = 1;
+            None => self.skip_to(self.data.len()),
         }
-        true
     }
 
     fn skip_whitespace(&mut self) {
-        while !self.eof() {
-            if is_whitespace(self.cur()) {
-                self.skip();
-                continue;
-            }
+        while self.skip_trivia().is_some() {}
+    }
 
-            macro_rules! skip_delimited {
-                ($start:literal, $end:literal) => {
-                    if self.take_string($start) {
-                        while !self.eof() && !self.take_string($end) {
-                            self.skip();
-                        }
-                        continue;
+    /// Skips a single run of whitespace, a comment, a declaration or a
+    /// processing instruction, returning what it was.
+    fn skip_trivia(&mut self) -> Option<Trivia> {
+        if self.eof() {
+            return None;
+        }
+
+        if is_whitespace(self.cur()) {
+            self.skip_while(is_whitespace);
+            return Some(Trivia::Whitespace);
+        }
+
+        macro_rules! skip_delimited {
+            ($start:literal, $end:literal, $kind:ident) => {
+                if self.take_string($start) {
+                    if !self.eof() {
+                        self.skip_past($end);
                     }
-                };
-            }
+                    return Some(Trivia::$kind);
+                }
+            };
+        }
+
+        skip_delimited!("<!--", "-->", Comment);
+        skip_delimited!("<!", ">", Declaration);
+        skip_delimited!("<?", "?>", ProcessingInstruction);
+        None
+    }
 
-            skip_delimited!("<!--", "-->");
-            skip_delimited!("<!", ">");
-            skip_delimited!("<?", "?>");
-            break;
+    /// Splits off the next piece of the input that the tokenizer skips
+    /// between tokens, or, if there is something else next, a single
+    /// character of it (with a `None` kind).
+    ///
+    /// Returns `None` at the end of the input.
+    pub fn next_trivia(&mut self) -> Option<(Option<Trivia>, &'s str)> {
+        if self.eof() {
+            return None;
+        }
+        let start = self.current_index;
+        let kind = self.skip_trivia();
+        if kind.is_none() {
+            self.skip();
         }
+        Some((kind, &self.data[start..self.current_index]))
     }
 
-    pub fn take(&mut self, expect: char) -> bool {
-        let ch = self.cur();
-        if ch == expect {
+    /// Consume the next character if it is the given ASCII character,
+    /// without skipping whitespace before it.
+    ///
+    /// This is how e.g. `/>` and `</` are recognized, `/ >` and `< /` are not
+    /// the same.
+    pub fn take_byte(&mut self, expect: u8) -> bool {
+        if self.cur() == expect && !self.eof() {
             self.skip();
             return true;
         }
         false
     }
 
+    /// Read the next token.
     pub fn next_token(&mut self) -> Token<'s> {
+        self.next_token_spanned().0
+    }
+
+    /// Read the next token along with its span, which excludes the
+    /// whitespace and comments skipped before it.
+    pub fn next_token_spanned(&mut self) -> (Token<'s>, Span) {
         self.skip_whitespace();
 
+        let start = self.current_index;
+        let token = self.lex_token();
+
+        (token, self.span_from(start))
+    }
+
+    fn lex_token(&mut self) -> Token<'s> {
         if self.eof() {
             return Token::Eof;
         }
 
-        let ch = self.cur();
+        let start = self.current_index;
+        let byte = self.cur();
         self.skip();
 
-        match ch {
-            '\0' => Token::Eof,
-            '<' => Token::OpenLess,
-            '>' => Token::CloseGreater,
-            '/' => Token::Slash,
-            '=' => Token::Equal,
-            '"' => Token::String({
-                let start_idx = self.current_index;
-                while !self.eof() && !self.take_string("\"") {
-                    self.skip();
+        match byte {
+            b'\0' => Token::Eof,
+            b'<' => Token::OpenLess,
+            b'>' => Token::CloseGreater,
+            b'/' => Token::Slash,
+            b'=' => Token::Equal,
+            b'"' => Token::Quoted({
+                let start = self.current_index;
+                if !self.eof() {
+                    self.skip_past("\"");
                 }
                 // -1 to exclude the closing quote
-                // (but avoid a panic if it was EOF)
-                &self.data[start_idx..self.current_index - !self.eof() as usize]
+                // (but keep it if it was the last character)
+                &self.data[start..self.current_index - !self.eof() as usize]
             }),
-            ch => Token::String({
-                // including the consumed ch
-                let start_idx = self.current_index - ch.len_utf8();
-                while !self.eof() && !is_punctuation_or_whitespace(self.cur()) {
-                    self.skip();
-                }
-                &self.data[start_idx..self.current_index]
+            _ => Token::String({
+                self.skip_while(|b| !is_punctuation_or_whitespace(b));
+                &self.data[start..self.current_index]
             }),
         }
     }
 }
 
+impl<'s> Iterator for Tokenizer<'s> {
+    type Item = (Token<'s>, Span);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_token_spanned() {
+            (Token::Eof, span) if span.start == span.end => None,
+            token => Some(token),
+        }
+    }
+}
+
+/// Whether the string is read back as a single [`Token::String`], i.e. it
+/// can be written as an element or attribute name.
+pub(crate) fn is_name(s: &str) -> bool {
+    !s.is_empty() && !s.starts_with(['"', '\0']) && !s.bytes().any(is_punctuation_or_whitespace)
+}
+
 #[inline]
-fn is_whitespace(c: char) -> bool {
-    c == ' ' || c == '\t' || c == '\n' || c == '\r'
+fn is_whitespace(b: u8) -> bool {
+    b == b' ' || b == b'\t' || b == b'\n' || b == b'\r'
 }
 
 #[inline]
-fn is_punctuation_or_whitespace(c: char) -> bool {
-    is_whitespace(c) || c == '<' || c == '>' || c == '=' || c == '/'
+fn is_punctuation_or_whitespace(b: u8) -> bool {
+    is_whitespace(b) || b == b'<' || b == b'>' || b == b'=' || b == b'/'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position_at_eof(s: &str) -> Position {
+        let mut tokenizer = Tokenizer::new(s);
+        while !matches!(tokenizer.next_token_spanned().0, Token::Eof) {}
+        tokenizer.position()
+    }
+
+    #[test]
+    fn position_at_eof_is_kept_as_it_was() {
+        // skipping the last character does not advance the position
+        assert_eq!(position_at_eof("a\nbé"), Position { line: 2, column: 2 });
+        // while taking a delimiter (even a partial one) does
+        assert_eq!(
+            position_at_eof("a\n<!--x-"),
+            Position { line: 2, column: 9 }
+        );
+        assert_eq!(
+            position_at_eof("a\n<!--x-->"),
+            Position { line: 2, column: 9 }
+        );
+        assert_eq!(position_at_eof("\"a\""), Position { line: 1, column: 4 });
+    }
 }
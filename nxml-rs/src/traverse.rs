@@ -0,0 +1,354 @@
+use std::{
+    fmt::Write,
+    iter::Enumerate,
+    slice::{Iter, IterMut},
+};
+
+use crate::element::{GenericElement, Map, NxmlStr};
+
+/// The order in which the descendants of an element are visited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Order {
+    /// Every element comes before its descendants, which is the order their
+    /// opening tags appear in the source.
+    Pre,
+    /// Every element comes after its descendants, which is the order their
+    /// closing tags appear in the source.
+    Post,
+}
+
+/// An element yielded by [`descendants`](GenericElement::descendants),
+/// together with where it is.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Descendant<'a, S: NxmlStr> {
+    /// The element itself.
+    pub element: &'a GenericElement<S>,
+    /// The path of child indices from the element the traversal started at,
+    /// same as the ones used by [`Editor`](crate::Editor) when it started at
+    /// the root.
+    pub path: Vec<usize>,
+}
+
+impl<S: NxmlStr> Descendant<'_, S> {
+    /// How deep the element is, with the children of the element the
+    /// traversal started at being at depth 1.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+}
+
+/// An element yielded by
+/// [`descendants_mut`](GenericElement::descendants_mut).
+///
+/// The children of the element are not accessible, as they are yielded
+/// separately, but everything else can be changed.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DescendantMut<'a, S: NxmlStr> {
+    /// The name of the element.
+    pub name: &'a mut S::Name,
+    /// The text content of the element.
+    pub text_content: &'a mut S::Text,
+    /// The attributes of the element.
+    pub attributes: &'a mut Map<S::Name, S::Value>,
+    /// The path of child indices from the element the traversal started at.
+    pub path: Vec<usize>,
+}
+
+impl<S: NxmlStr> DescendantMut<'_, S> {
+    /// How deep the element is, see [`Descendant::depth`].
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+}
+
+impl<S: NxmlStr> GenericElement<S> {
+    /// Iterate over all of the elements below this one, every element
+    /// coming before its children.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = nxml_ref!(<Entity><Entity><SpriteComponent/></Entity><LuaComponent/></Entity>);
+    ///
+    /// let visited = element
+    ///     .descendants()
+    ///     .map(|d| format!("{}{}", "-".repeat(d.depth()), d.element.name))
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(visited, ["-Entity", "--SpriteComponent", "-LuaComponent"]);
+    /// ```
+    pub fn descendants(&self) -> Descendants<'_, S> {
+        Descendants::new(self, Order::Pre)
+    }
+
+    /// Iterate over all of the elements below this one, every element
+    /// coming after its children.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = nxml_ref!(<Entity><Entity><SpriteComponent/></Entity><LuaComponent/></Entity>);
+    ///
+    /// let paths = element
+    ///     .descendants_post_order()
+    ///     .map(|d| d.path)
+    ///     .collect::<Vec<_>>();
+    ///
+    /// assert_eq!(paths, [vec![0, 0], vec![0], vec![1]]);
+    /// ```
+    pub fn descendants_post_order(&self) -> Descendants<'_, S> {
+        Descendants::new(self, Order::Post)
+    }
+
+    /// Mutable version of [`descendants`](Self::descendants).
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let mut element = nxml!(<Entity><Entity><Sprite/></Entity><Sprite/></Entity>);
+    ///
+    /// for d in element.descendants_mut() {
+    ///     if *d.name == "Sprite" {
+    ///         *d.name = "SpriteComponent".into();
+    ///     }
+    /// }
+    ///
+    /// assert_eq!(element.to_string(), "<Entity><Entity><SpriteComponent/></Entity><SpriteComponent/></Entity>");
+    /// ```
+    pub fn descendants_mut(&mut self) -> DescendantsMut<'_, S> {
+        DescendantsMut::new(self, Order::Pre)
+    }
+
+    /// Mutable version of
+    /// [`descendants_post_order`](Self::descendants_post_order).
+    pub fn descendants_post_order_mut(&mut self) -> DescendantsMut<'_, S> {
+        DescendantsMut::new(self, Order::Post)
+    }
+
+    /// Get the element at the path of child indices, with the empty path
+    /// being this element itself.
+    pub fn at_path(&self, path: &[usize]) -> Option<&Self> {
+        path.iter().try_fold(self, |e, &i| e.children.get(i))
+    }
+
+    /// Mutable version of [`at_path`](Self::at_path).
+    pub fn at_path_mut(&mut self, path: &[usize]) -> Option<&mut Self> {
+        path.iter().try_fold(self, |e, &i| e.children.get_mut(i))
+    }
+
+    /// Describe the path of child indices with the names of the elements
+    /// along it, starting with this one, e.g. `Entity/Entity[2]/Sprite`.
+    ///
+    /// The number is the position of the element among the siblings with
+    /// the same name, counting from one, and is only given when there is more
+    /// than one of them.
+    ///
+    /// Returns `None` if there is no element at the path.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use nxml_rs::*;
+    /// let element = nxml_ref!(<Entity><Entity/><Sprite/><Entity><Sprite/></Entity></Entity>);
+    ///
+    /// assert_eq!(element.describe_path(&[2, 0]).unwrap(), "Entity/Entity[2]/Sprite");
+    /// assert_eq!(element.describe_path(&[1]).unwrap(), "Entity/Sprite");
+    /// assert_eq!(element.describe_path(&[1, 0]), None);
+    /// ```
+    pub fn describe_path(&self, path: &[usize]) -> Option<String> {
+        let mut described = self.name.as_ref().to_owned();
+        let mut element = self;
+        for &index in path {
+            let child = element.children.get(index)?;
+            let name = child.name.as_ref();
+            let same_name = |c: &&Self| c.name.as_ref() == name;
+
+            write!(described, "/{name}").unwrap();
+            if element.children.iter().filter(same_name).count() > 1 {
+                let position = element.children[..index].iter().filter(same_name).count();
+                write!(described, "[{}]", position + 1).unwrap();
+            }
+            element = child;
+        }
+        Some(described)
+    }
+}
+
+type Children<'a, S> = Enumerate<Iter<'a, GenericElement<S>>>;
+type ChildrenMut<'a, S> = Enumerate<IterMut<'a, GenericElement<S>>>;
+
+/// An iterator over the descendants of an element, created by
+/// [`descendants`](GenericElement::descendants) or
+/// [`descendants_post_order`](GenericElement::descendants_post_order).
+#[derive(Debug)]
+pub struct Descendants<'a, S: NxmlStr> {
+    order: Order,
+    stack: Vec<(&'a GenericElement<S>, Children<'a, S>)>,
+    path: Vec<usize>,
+}
+
+impl<'a, S: NxmlStr> Descendants<'a, S> {
+    fn new(element: &'a GenericElement<S>, order: Order) -> Self {
+        Self {
+            order,
+            stack: vec![(element, element.children.iter().enumerate())],
+            path: Vec::new(),
+        }
+    }
+}
+
+impl<'a, S: NxmlStr> Iterator for Descendants<'a, S> {
+    type Item = Descendant<'a, S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (_, children) = self.stack.last_mut()?;
+            if let Some((index, element)) = children.next() {
+                self.path.push(index);
+                self.stack
+                    .push((element, element.children.iter().enumerate()));
+                if self.order == Order::Pre {
+                    let path = self.path.clone();
+                    return Some(Descendant { element, path });
+                }
+                continue;
+            }
+            let (element, _) = self.stack.pop()?;
+            if self.stack.is_empty() {
+                // the element the traversal started at
+                return None;
+            }
+            let path = self.path.clone();
+            self.path.pop();
+            if self.order == Order::Post {
+                return Some(Descendant { element, path });
+            }
+        }
+    }
+}
+
+type Fields<'a, S> = (
+    &'a mut <S as NxmlStr>::Name,
+    &'a mut <S as NxmlStr>::Text,
+    &'a mut Map<<S as NxmlStr>::Name, <S as NxmlStr>::Value>,
+);
+
+/// A mutable iterator over the descendants of an element, created by
+/// [`descendants_mut`](GenericElement::descendants_mut) or
+/// [`descendants_post_order_mut`](GenericElement::descendants_post_order_mut).
+#[derive(Debug)]
+pub struct DescendantsMut<'a, S: NxmlStr> {
+    order: Order,
+    /// The fields of the elements on the current path that are yet to be
+    /// yielded in post-order, and their children.
+    stack: Vec<(Option<Fields<'a, S>>, ChildrenMut<'a, S>)>,
+    path: Vec<usize>,
+}
+
+impl<'a, S: NxmlStr> DescendantsMut<'a, S> {
+    fn new(element: &'a mut GenericElement<S>, order: Order) -> Self {
+        Self {
+            order,
+            stack: vec![(None, element.children.iter_mut().enumerate())],
+            path: Vec::new(),
+        }
+    }
+}
+
+impl<'a, S: NxmlStr> Iterator for DescendantsMut<'a, S> {
+    type Item = DescendantMut<'a, S>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (_, children) = self.stack.last_mut()?;
+            if let Some((index, element)) = children.next() {
+                // split the borrow, so that the children can be yielded
+                // separately from the rest of the element
+                let GenericElement {
+                    name,
+                    text_content,
+                    attributes,
+                    children,
+                } = element;
+                self.path.push(index);
+                let fields = (name, text_content, attributes);
+                if self.order == Order::Pre {
+                    self.stack.push((None, children.iter_mut().enumerate()));
+                    return Some(DescendantMut::new(fields, self.path.clone()));
+                }
+                self.stack
+                    .push((Some(fields), children.iter_mut().enumerate()));
+                continue;
+            }
+            let (fields, _) = self.stack.pop()?;
+            if self.stack.is_empty() {
+                return None;
+            }
+            let path = self.path.clone();
+            self.path.pop();
+            if let Some(fields) = fields {
+                return Some(DescendantMut::new(fields, path));
+            }
+        }
+    }
+}
+
+impl<'a, S: NxmlStr> DescendantMut<'a, S> {
+    fn new((name, text_content, attributes): Fields<'a, S>, path: Vec<usize>) -> Self {
+        Self {
+            name,
+            text_content,
+            attributes,
+            path,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse;
+
+    #[test]
+    fn orders_and_paths() {
+        let mut element = parse("<r><a><b/><c/></a><d><e/></d></r>").unwrap();
+
+        let pre = element
+            .descendants()
+            .map(|d| (d.element.name, d.path))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            pre,
+            [
+                ("a", vec![0]),
+                ("b", vec![0, 0]),
+                ("c", vec![0, 1]),
+                ("d", vec![1]),
+                ("e", vec![1, 0]),
+            ]
+        );
+
+        let post = element
+            .descendants_post_order_mut()
+            .map(|d| (*d.name, d.path))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            post,
+            [
+                ("b", vec![0, 0]),
+                ("c", vec![0, 1]),
+                ("a", vec![0]),
+                ("e", vec![1, 0]),
+                ("d", vec![1]),
+            ]
+        );
+
+        for d in element.descendants_mut() {
+            *d.text_content = d.depth().to_string().into();
+        }
+        assert_eq!(
+            element.to_string(),
+            "<r><a>1<b>2</b><c>2</c></a><d>1<e>2</e></d></r>"
+        );
+        assert_eq!(element.at_path(&[1, 0]).unwrap().name, "e");
+        assert!(parse("<r/>").unwrap().descendants().next().is_none());
+    }
+}
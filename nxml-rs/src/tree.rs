@@ -0,0 +1,427 @@
+use std::{
+    borrow::Cow,
+    fmt::{self, Display},
+    ops::{Div, Range, Rem},
+};
+
+use crate::{
+    element::{Element, ElementRef, Text},
+    options::DuplicatePolicy,
+    parser::{deduplicate, has_repeats, NxmlError},
+    reader::{Event, Reader, StartTag},
+    tokenizer::Span,
+};
+
+/// An element tree stored in a couple of flat tables instead of a
+/// [`Vec`] of children and a map of attributes per element.
+///
+/// Parsing into it only allocates a handful of times no matter how big the
+/// source is, which makes a difference when parsing a lot of files.
+///
+/// The elements are navigated through [`TreeElement`] handles, which have
+/// the same accessors as [`ElementRef`].
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// let tree = Tree::parse(r#"
+///     <Entity name="player">
+///         <SpriteComponent image_file="a.png"/>
+///         <LuaComponent script_source_file="a.lua">hello</LuaComponent>
+///     </Entity>
+/// "#).unwrap();
+///
+/// let root = tree.root();
+///
+/// assert_eq!(root % "name", "player");
+/// assert_eq!(root / "LuaComponent" % "script_source_file", "a.lua");
+/// assert_eq!(root / "LuaComponent" % Text, "hello");
+/// assert_eq!(root.elements().map(|e| e.name()).collect::<Vec<_>>(), ["SpriteComponent", "LuaComponent"]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tree<'s> {
+    /// The elements in the order their opening tags appear in the source,
+    /// so every element is followed by all of its descendants.
+    elements: Vec<Entry<'s>>,
+    attributes: Vec<(&'s str, &'s str)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry<'s> {
+    name: &'s str,
+    text_content: Cow<'s, str>,
+    attributes: Range<usize>,
+    parent: Option<usize>,
+    /// The index after the last descendant, which is also the index of the
+    /// next sibling if there is one.
+    end: usize,
+}
+
+impl<'s> Tree<'s> {
+    /// Parse the source, see [`parse`](crate::parse).
+    pub fn parse(s: &'s str) -> Result<Self, NxmlError> {
+        Self::read(&mut Reader::new(s))
+    }
+
+    /// Lenient version of [`Tree::parse`], see
+    /// [`parse_lenient`](crate::parse_lenient).
+    pub fn parse_lenient(s: &'s str) -> (Self, Vec<NxmlError>) {
+        let mut reader = Reader::new(s).lenient();
+        let tree = Self::read(&mut reader).expect("lenient parser never errors");
+        (tree, reader.take_errors())
+    }
+
+    fn read(reader: &mut Reader<'s>) -> Result<Self, NxmlError> {
+        let mut tree = Tree {
+            elements: Vec::new(),
+            attributes: Vec::new(),
+        };
+        let mut stack: Vec<Open> = Vec::new();
+
+        while let Some(event) = reader.next_event() {
+            match event? {
+                Event::StartElement(tag) => {
+                    let index = tree.start(tag, stack.last().map(|o| o.index), reader);
+                    stack.push(Open {
+                        index,
+                        tokens: 0,
+                        text_end: None,
+                    });
+                }
+                Event::SelfClose(tag) => {
+                    let index = tree.start(tag, stack.last().map(|o| o.index), reader);
+                    tree.elements[index].end = index + 1;
+                }
+                Event::Text { text, span } => {
+                    if let Some(open) = stack.last_mut() {
+                        let text_content = &mut tree.elements[open.index].text_content;
+                        open.push_text(text_content, text, span, reader.source());
+                    }
+                }
+                Event::EndElement { .. } => {
+                    if let Some(open) = stack.pop() {
+                        tree.elements[open.index].end = tree.elements.len();
+                    }
+                }
+            }
+        }
+        assert!(
+            !tree.elements.is_empty(),
+            "the reader always produces a root element"
+        );
+        Ok(tree)
+    }
+
+    fn start(
+        &mut self,
+        tag: StartTag<'s>,
+        parent: Option<usize>,
+        reader: &mut Reader<'s>,
+    ) -> usize {
+        let start = self.attributes.len();
+        let attributes = tag.attributes.iter().map(|a| (a.key, a.value));
+        if has_repeats(tag.attributes.iter().map(|a| a.key)) {
+            // same as inserting into the map, the last value is kept in the
+            // position of the first one
+            let unique = deduplicate(attributes, DuplicatePolicy::KeepLast);
+            self.attributes.extend(unique);
+        } else {
+            self.attributes.extend(attributes);
+        }
+        reader.recycle(tag.attributes);
+
+        let index = self.elements.len();
+        self.elements.push(Entry {
+            name: tag.name,
+            text_content: Cow::Borrowed(""),
+            attributes: start..self.attributes.len(),
+            parent,
+            end: index + 1,
+        });
+        index
+    }
+
+    /// The root element.
+    pub fn root(&self) -> TreeElement<'_, 's> {
+        TreeElement {
+            tree: self,
+            index: 0,
+        }
+    }
+
+    /// The number of elements in the tree, including the root.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// All of the elements, in the order their opening tags appear in the
+    /// source.
+    pub fn iter(&self) -> impl Iterator<Item = TreeElement<'_, 's>> {
+        (0..self.elements.len()).map(|index| TreeElement { tree: self, index })
+    }
+}
+
+/// An element that is still being read.
+struct Open {
+    index: usize,
+    /// How many text tokens were joined into the text, which decides how the
+    /// next one is joined, same as in
+    /// [`push_text`](crate::parser::push_text).
+    tokens: usize,
+    /// Where the text ends in the source, while it is borrowed from it.
+    text_end: Option<usize>,
+}
+
+impl Open {
+    /// Joins the text token the same way as
+    /// [`push_text`](crate::parser::push_text), but keeps
+    /// borrowing the source for as long as the text is written there exactly
+    /// as it is joined, e.g. with a single space between the first two words,
+    /// so that the text of most elements takes no allocation.
+    fn push_text<'s>(
+        &mut self,
+        text_content: &mut Cow<'s, str>,
+        text: &'s str,
+        span: Span,
+        source: &'s str,
+    ) {
+        // the value of a quoted token starts after the quote
+        let start = span.start + usize::from(source[span.range()].starts_with('"'));
+        let separator = match self.tokens {
+            0 => {
+                if !text.is_empty() {
+                    self.tokens = 1;
+                    *text_content = Cow::Borrowed(text);
+                    self.text_end = Some(start + text.len());
+                }
+                return;
+            }
+            1 => " ",
+            _ => "",
+        };
+        self.tokens += 1;
+        match (&*text_content, self.text_end) {
+            (Cow::Borrowed(content), Some(end)) if &source[end..start] == separator => {
+                let (content_start, text_end) = (end - content.len(), start + text.len());
+                *text_content = Cow::Borrowed(&source[content_start..text_end]);
+                self.text_end = Some(text_end);
+            }
+            _ => {
+                let joined = text_content.to_mut();
+                joined.push_str(separator);
+                joined.push_str(text);
+                self.text_end = None;
+            }
+        }
+    }
+}
+
+/// A handle to an element of a [`Tree`].
+///
+/// It is [`Copy`], so the accessors and the `/` and `%` operators take it
+/// by value.
+#[derive(Debug, Clone, Copy)]
+pub struct TreeElement<'t, 's> {
+    tree: &'t Tree<'s>,
+    index: usize,
+}
+
+impl PartialEq for TreeElement<'_, '_> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.tree, other.tree) && self.index == other.index
+    }
+}
+
+impl Eq for TreeElement<'_, '_> {}
+
+impl<'t, 's> TreeElement<'t, 's> {
+    fn entry(&self) -> &'t Entry<'s> {
+        &self.tree.elements[self.index]
+    }
+
+    fn at(&self, index: usize) -> Self {
+        Self {
+            tree: self.tree,
+            index,
+        }
+    }
+
+    /// The name of the element, same as [`ElementRef::name`].
+    pub fn name(&self) -> &'s str {
+        self.entry().name
+    }
+
+    /// The text content of the element, same as
+    /// [`ElementRef::text_content`].
+    pub fn text_content(&self) -> &'t str {
+        &self.entry().text_content
+    }
+
+    /// The attributes of the element, in the order they appear in the
+    /// source.
+    pub fn attributes(&self) -> &'t [(&'s str, &'s str)] {
+        &self.tree.attributes[self.entry().attributes.clone()]
+    }
+
+    /// A shorthand for getting an attribute value.
+    pub fn attr(&self, key: &str) -> Option<&'s str> {
+        self.attributes()
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| *v)
+    }
+
+    /// The parent element, or `None` for the root.
+    pub fn parent(&self) -> Option<Self> {
+        self.entry().parent.map(|index| self.at(index))
+    }
+
+    /// Iterate over all child elements.
+    pub fn elements(&self) -> impl Iterator<Item = Self> + 't {
+        let this = *self;
+        let end = self.entry().end;
+        let mut next = self.index + 1;
+        std::iter::from_fn(move || {
+            if next >= end {
+                return None;
+            }
+            let child = this.at(next);
+            next = child.entry().end;
+            Some(child)
+        })
+    }
+
+    /// Find the first child element with the given name.
+    pub fn child(&self, name: &str) -> Option<Self> {
+        self.elements().find(|c| c.name() == name)
+    }
+
+    /// Iterate over all child elements with the given name.
+    pub fn children<'a>(&self, name: &'a str) -> impl Iterator<Item = Self> + 'a
+    where
+        't: 'a,
+    {
+        self.elements().filter(move |c| c.name() == name)
+    }
+
+    /// Convert to an [`ElementRef`], which allocates for every element.
+    pub fn to_element_ref(&self) -> ElementRef<'s> {
+        let entry = self.entry();
+        let mut element = ElementRef::new(entry.name);
+        element.text_content = entry.text_content.clone();
+        element.attributes = self.attributes().iter().copied().collect();
+        element.children = self.elements().map(|c| c.to_element_ref()).collect();
+        element
+    }
+
+    /// Convert to an owned [`Element`], see
+    /// [`to_element_ref`](Self::to_element_ref).
+    pub fn to_owned(&self) -> Element {
+        self.to_element_ref().to_owned()
+    }
+}
+
+impl<'t, 's> Div<&str> for TreeElement<'t, 's> {
+    type Output = Self;
+
+    /// A chainable child element accessor, same as for [`ElementRef`].
+    fn div(self, rhs: &str) -> Self::Output {
+        match self.child(rhs) {
+            Some(child) => child,
+            None => panic!("child element '{rhs}' not found"),
+        }
+    }
+}
+
+impl<'t, 's> Rem<&str> for TreeElement<'t, 's> {
+    type Output = &'s str;
+
+    /// A shorthand for getting an attribute value, same as for
+    /// [`ElementRef`].
+    fn rem(self, rhs: &str) -> Self::Output {
+        match self.attr(rhs) {
+            Some(attr) => attr,
+            None => panic!("attribute '{rhs}' not found"),
+        }
+    }
+}
+
+impl<'t, 's> Rem<Text> for TreeElement<'t, 's> {
+    type Output = &'t str;
+
+    /// A shorthand for getting the text content.
+    fn rem(self, _: Text) -> Self::Output {
+        self.text_content()
+    }
+}
+
+impl Display for TreeElement<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.to_element_ref().fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn same_as_element_ref() {
+        let source = r#"
+            <Entity a="1" b="2" a="3">
+                some <A x="y"> text </A>
+                <B><C/><D>d</D></B>
+                more
+                <C/>
+            </Entity>
+        "#;
+
+        let tree = Tree::parse(source).unwrap();
+
+        assert_eq!(tree.len(), 6);
+        assert_eq!(tree.root().to_element_ref(), crate::parse(source).unwrap());
+        assert_eq!(tree.root().attributes(), [("a", "3"), ("b", "2")]);
+
+        let d = tree.root() / "B" / "D";
+        assert_eq!(d.parent().and_then(|b| b.parent()), Some(tree.root()));
+        assert_eq!(tree.root().children("C").count(), 1);
+        assert_eq!(
+            tree.iter().map(|e| e.name()).collect::<String>(),
+            "EntityABCDC"
+        );
+    }
+
+    #[test]
+    fn borrows_text_written_as_it_is_read() {
+        let cases = [
+            ("<a>hello world</a>", true),
+            ("<a>x y=z</a>", true),
+            ("<a>\"hello\"</a>", true),
+            ("<a>hello  world</a>", false),
+            ("<a>hello world again</a>", false),
+            ("<a>hello <b/>world</a>", false),
+        ];
+        for (source, borrowed) in cases {
+            let tree = Tree::parse(source).unwrap();
+            let text = &tree.elements[0].text_content;
+
+            assert_eq!(matches!(text, Cow::Borrowed(_)), borrowed, "{source}");
+            assert_eq!(text, &crate::parse(source).unwrap().text_content);
+        }
+    }
+
+    #[test]
+    fn lenient_is_same_as_element_ref() {
+        let source = "<a><b></c><d x=/>text";
+
+        let (tree, errors) = Tree::parse_lenient(source);
+        let (element, expected) = crate::parse_lenient(source);
+
+        assert_eq!(tree.root().to_element_ref(), element);
+        assert_eq!(
+            errors.iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+            expected.iter().map(|e| e.to_string()).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,496 @@
+use std::{mem, slice::Iter};
+
+use crate::element::{GenericElement, NxmlStr};
+
+/// What to do after entering an element, returned by
+/// [`Visitor::enter_element`] and [`VisitorMut::enter_element`].
+///
+/// Whatever is skipped, the [`leave_element`](Visitor::leave_element) of the
+/// element is still called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Walk {
+    /// Visit the attributes, the text and the children of the element.
+    #[default]
+    Continue,
+    /// Visit the attributes and the text, but not the children of the
+    /// element.
+    SkipChildren,
+    /// Visit neither the attributes and the text, nor the children of the
+    /// element.
+    Skip,
+}
+
+/// A pass over an element tree, driven by [`walk`](GenericElement::walk).
+///
+/// For every element, [`enter_element`](Self::enter_element) is called
+/// first, then [`enter_attribute`](Self::enter_attribute) and
+/// [`leave_attribute`](Self::leave_attribute) for each attribute,
+/// [`enter_text`](Self::enter_text) and [`leave_text`](Self::leave_text) if
+/// there is any text, the same for every child, and
+/// [`leave_element`](Self::leave_element) last.
+///
+/// All of the hooks do nothing by default, and returning an error from any
+/// of them stops the walk.
+///
+/// Attributes and text have nothing inside of them, so their leave hooks
+/// come right after the enter ones. They are skipped by returning
+/// [`Walk::Skip`] from the `enter_element` of their element.
+///
+/// The walk keeps its own stack instead of recursing, so it works for trees
+/// of any depth.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// # use std::convert::Infallible;
+/// #[derive(Default)]
+/// struct Files<'a>(Vec<&'a str>);
+///
+/// impl<'a, 's> Visitor<'a, &'s str> for Files<'a> {
+///     type Error = Infallible;
+///
+///     fn enter_attribute(&mut self, _: &'a str, value: &'a str) -> Result<(), Infallible> {
+///         if value.ends_with(".lua") || value.ends_with(".png") {
+///             self.0.push(value);
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let element = nxml_ref!(<Entity>
+///     <SpriteComponent image_file="a.png"/>
+///     <LuaComponent script_source_file="a.lua"/>
+/// </Entity>);
+///
+/// let mut files = Files::default();
+/// element.walk(&mut files).unwrap();
+///
+/// assert_eq!(files.0, ["a.png", "a.lua"]);
+/// ```
+pub trait Visitor<'a, S: NxmlStr> {
+    /// The error that aborts the walk, use
+    /// [`Infallible`](std::convert::Infallible) if there is none.
+    type Error;
+
+    /// Called before anything else of the element is visited.
+    fn enter_element(&mut self, element: &'a GenericElement<S>) -> Result<Walk, Self::Error> {
+        let _ = element;
+        Ok(Walk::Continue)
+    }
+
+    /// Called after everything else of the element is visited.
+    fn leave_element(&mut self, element: &'a GenericElement<S>) -> Result<(), Self::Error> {
+        let _ = element;
+        Ok(())
+    }
+
+    /// Called for every attribute of the element that was entered last.
+    fn enter_attribute(&mut self, key: &'a str, value: &'a str) -> Result<(), Self::Error> {
+        let _ = (key, value);
+        Ok(())
+    }
+
+    /// Called right after [`enter_attribute`](Self::enter_attribute).
+    fn leave_attribute(&mut self, key: &'a str, value: &'a str) -> Result<(), Self::Error> {
+        let _ = (key, value);
+        Ok(())
+    }
+
+    /// Called with the text content of the element that was entered last,
+    /// if it is not empty.
+    fn enter_text(&mut self, text: &'a str) -> Result<(), Self::Error> {
+        let _ = text;
+        Ok(())
+    }
+
+    /// Called right after [`enter_text`](Self::enter_text).
+    fn leave_text(&mut self, text: &'a str) -> Result<(), Self::Error> {
+        let _ = text;
+        Ok(())
+    }
+}
+
+/// A pass that changes an element tree, driven by
+/// [`walk_mut`](GenericElement::walk_mut).
+///
+/// The hooks are called in the same order as the ones of [`Visitor`], and
+/// the children are visited after
+/// [`enter_element`](Self::enter_element) returns, so it can add, remove or
+/// replace them.
+///
+/// # Example
+/// ```rust
+/// # use nxml_rs::*;
+/// struct StripDebug;
+///
+/// impl<S: NxmlStr> VisitorMut<S> for StripDebug {
+///     type Error = String;
+///
+///     fn enter_element(&mut self, element: &mut GenericElement<S>) -> Result<Walk, String> {
+///         if element.name.as_ref() == "DebugComponent" {
+///             return Err("debug components are not allowed".into());
+///         }
+///         element.remove_attr("_debug");
+///         Ok(Walk::Continue)
+///     }
+/// }
+///
+/// let mut element = nxml!(<Entity _debug="1"><Sprite _debug="2" image_file="a.png"/></Entity>);
+/// element.walk_mut(&mut StripDebug).unwrap();
+///
+/// assert_eq!(element.to_string(), "<Entity><Sprite image_file=\"a.png\"/></Entity>");
+///
+/// let mut element = nxml!(<Entity><DebugComponent/></Entity>);
+/// assert_eq!(element.walk_mut(&mut StripDebug).unwrap_err(), "debug components are not allowed");
+/// ```
+pub trait VisitorMut<S: NxmlStr> {
+    /// The error that aborts the walk, use
+    /// [`Infallible`](std::convert::Infallible) if there is none.
+    type Error;
+
+    /// Called before anything else of the element is visited.
+    fn enter_element(&mut self, element: &mut GenericElement<S>) -> Result<Walk, Self::Error> {
+        let _ = element;
+        Ok(Walk::Continue)
+    }
+
+    /// Called after everything else of the element is visited.
+    fn leave_element(&mut self, element: &mut GenericElement<S>) -> Result<(), Self::Error> {
+        let _ = element;
+        Ok(())
+    }
+
+    /// Called for every attribute of the element that was entered last.
+    fn enter_attribute(&mut self, key: &S::Name, value: &mut S::Value) -> Result<(), Self::Error> {
+        let _ = (key, value);
+        Ok(())
+    }
+
+    /// Called right after [`enter_attribute`](Self::enter_attribute), with
+    /// the value as it left it.
+    fn leave_attribute(&mut self, key: &S::Name, value: &mut S::Value) -> Result<(), Self::Error> {
+        let _ = (key, value);
+        Ok(())
+    }
+
+    /// Called with the text content of the element that was entered last,
+    /// if it is not empty.
+    fn enter_text(&mut self, text: &mut S::Text) -> Result<(), Self::Error> {
+        let _ = text;
+        Ok(())
+    }
+
+    /// Called right after [`enter_text`](Self::enter_text), with the text
+    /// as it left it.
+    fn leave_text(&mut self, text: &mut S::Text) -> Result<(), Self::Error> {
+        let _ = text;
+        Ok(())
+    }
+}
+
+impl<S: NxmlStr> GenericElement<S> {
+    /// Walk the element and all of its descendants with the visitor, see
+    /// [`Visitor`].
+    pub fn walk<'a, V: Visitor<'a, S>>(&'a self, visitor: &mut V) -> Result<(), V::Error> {
+        // the elements that were entered but not left yet, and their
+        // children that are yet to be visited
+        let mut stack = vec![(self, self.enter(visitor)?)];
+
+        while let Some((element, children)) = stack.last_mut() {
+            match children.next() {
+                Some(child) => stack.push((child, child.enter(visitor)?)),
+                None => {
+                    visitor.leave_element(element)?;
+                    stack.pop();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Enter the element, returning the children to visit.
+    fn enter<'a, V: Visitor<'a, S>>(&'a self, visitor: &mut V) -> Result<Iter<'a, Self>, V::Error> {
+        let walk = visitor.enter_element(self)?;
+        if walk != Walk::Skip {
+            for (key, value) in &self.attributes {
+                visitor.enter_attribute(key.as_ref(), value.as_ref())?;
+                visitor.leave_attribute(key.as_ref(), value.as_ref())?;
+            }
+            if !self.text_content.as_ref().is_empty() {
+                visitor.enter_text(self.text_content.as_ref())?;
+                visitor.leave_text(self.text_content.as_ref())?;
+            }
+        }
+        match walk {
+            Walk::Continue => Ok(self.children.iter()),
+            Walk::SkipChildren | Walk::Skip => Ok([].iter()),
+        }
+    }
+
+    /// Walk the element and all of its descendants with the visitor, which
+    /// can change them, see [`VisitorMut`].
+    pub fn walk_mut<V: VisitorMut<S>>(&mut self, visitor: &mut V) -> Result<(), V::Error> {
+        if !self.enter_mut(visitor)? {
+            return visitor.leave_element(self);
+        }
+        let children = mem::take(&mut self.children);
+        let mut open = OpenChildren {
+            root: self,
+            stack: vec![(children, 0)],
+        };
+
+        while let Some((children, next)) = open.stack.last_mut() {
+            let Some(child) = children.get_mut(*next) else {
+                visitor.leave_element(open.close())?;
+                continue;
+            };
+            *next += 1;
+            if child.enter_mut(visitor)? {
+                let children = mem::take(&mut child.children);
+                open.stack.push((children, 0));
+            } else {
+                visitor.leave_element(child)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Enter the element, returning whether to visit its children.
+    fn enter_mut<V: VisitorMut<S>>(&mut self, visitor: &mut V) -> Result<bool, V::Error> {
+        let walk = visitor.enter_element(self)?;
+        if walk != Walk::Skip {
+            for (key, value) in &mut self.attributes {
+                visitor.enter_attribute(key, value)?;
+                visitor.leave_attribute(key, value)?;
+            }
+            if !self.text_content.as_ref().is_empty() {
+                visitor.enter_text(&mut self.text_content)?;
+                visitor.leave_text(&mut self.text_content)?;
+            }
+        }
+        Ok(walk == Walk::Continue)
+    }
+}
+
+/// The children of the elements that [`walk_mut`](GenericElement::walk_mut)
+/// entered but did not leave yet, taken out of them so that they can be
+/// borrowed separately, and the index of the next child to visit for each.
+///
+/// Every element gets its children back before it is left, or when the walk
+/// stops early because of an error or a panic.
+struct OpenChildren<'a, S: NxmlStr> {
+    root: &'a mut GenericElement<S>,
+    stack: Vec<(Vec<GenericElement<S>>, usize)>,
+}
+
+impl<S: NxmlStr> OpenChildren<'_, S> {
+    /// Give the children on top of the stack back to their element, and
+    /// return that element.
+    fn close(&mut self) -> &mut GenericElement<S> {
+        let (children, _) = self.stack.pop().expect("there are open children");
+        let element = match self.stack.last_mut() {
+            // the element is the child that was visited last
+            Some((siblings, next)) => &mut siblings[*next - 1],
+            None => &mut *self.root,
+        };
+        element.children = children;
+        element
+    }
+}
+
+impl<S: NxmlStr> Drop for OpenChildren<'_, S> {
+    fn drop(&mut self) {
+        while !self.stack.is_empty() {
+            self.close();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{element::IntoNxmlStr, parse, Element, ElementRef};
+
+    #[derive(Default)]
+    struct Trace(Vec<String>);
+
+    impl<'a, 's> Visitor<'a, &'s str> for Trace {
+        type Error = &'s str;
+
+        fn enter_element(&mut self, element: &'a GenericElement<&'s str>) -> Result<Walk, &'s str> {
+            self.0.push(format!("<{}>", element.name));
+            match element.name {
+                "skip" => Ok(Walk::SkipChildren),
+                "hide" => Ok(Walk::Skip),
+                "stop" => Err(element.name),
+                _ => Ok(Walk::Continue),
+            }
+        }
+
+        fn leave_element(&mut self, element: &'a GenericElement<&'s str>) -> Result<(), &'s str> {
+            self.0.push(format!("</{}>", element.name));
+            Ok(())
+        }
+
+        fn enter_attribute(&mut self, key: &'a str, value: &'a str) -> Result<(), &'s str> {
+            self.0.push(format!("{key}={value}"));
+            Ok(())
+        }
+
+        fn leave_attribute(&mut self, key: &'a str, _: &'a str) -> Result<(), &'s str> {
+            self.0.push(format!("/{key}"));
+            Ok(())
+        }
+
+        fn enter_text(&mut self, text: &'a str) -> Result<(), &'s str> {
+            self.0.push(text.into());
+            Ok(())
+        }
+
+        fn leave_text(&mut self, _: &'a str) -> Result<(), &'s str> {
+            self.0.push("/text".into());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn order_skip_and_abort() {
+        let source =
+            r#"<a x="1">t<skip y="2">u<b/></skip><hide z="3">v<b/></hide><c/><stop/><d/></a>"#;
+        let element = parse(source).unwrap();
+
+        let mut trace = Trace::default();
+        assert_eq!(element.walk(&mut trace), Err("stop"));
+        assert_eq!(
+            trace.0,
+            [
+                "<a>", "x=1", "/x", "t", "/text", "<skip>", "y=2", "/y", "u", "/text", "</skip>",
+                "<hide>", "</hide>", "<c>", "</c>", "<stop>"
+            ]
+        );
+    }
+
+    /// Renames the elements and replaces the attribute values and the text,
+    /// counting the attributes, without going into the renamed elements.
+    struct Rename(usize);
+
+    impl<S: NxmlStr> VisitorMut<S> for Rename
+    where
+        for<'b> &'b str: IntoNxmlStr<S>,
+    {
+        type Error = std::convert::Infallible;
+
+        fn enter_element(&mut self, element: &mut GenericElement<S>) -> Result<Walk, Self::Error> {
+            if element.name.as_ref() != "old" {
+                return Ok(Walk::Continue);
+            }
+            element.name = "new".into_name();
+            Ok(Walk::SkipChildren)
+        }
+
+        fn enter_attribute(
+            &mut self,
+            _: &S::Name,
+            value: &mut S::Value,
+        ) -> Result<(), Self::Error> {
+            self.0 += 1;
+            *value = "v".into_value();
+            Ok(())
+        }
+
+        fn leave_attribute(
+            &mut self,
+            _: &S::Name,
+            value: &mut S::Value,
+        ) -> Result<(), Self::Error> {
+            assert_eq!(value.as_ref(), "v");
+            Ok(())
+        }
+
+        fn leave_text(&mut self, text: &mut S::Text) -> Result<(), Self::Error> {
+            *text = "changed".into_text();
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn walk_mut_changes_in_place() {
+        let mut element: Element = parse(r#"<a x="1">t<old y="2"><old/></old></a>"#)
+            .unwrap()
+            .to_owned();
+
+        let mut rename = Rename(0);
+        element.walk_mut(&mut rename).unwrap();
+
+        assert_eq!(rename.0, 2);
+        assert_eq!(
+            element.to_string(),
+            r#"<a x="v">changed<new y="v"><old/></new></a>"#
+        );
+    }
+
+    struct Stop;
+
+    impl<S: NxmlStr> VisitorMut<S> for Stop {
+        type Error = ();
+
+        fn enter_element(&mut self, element: &mut GenericElement<S>) -> Result<Walk, ()> {
+            match element.name.as_ref() {
+                "stop" => Err(()),
+                _ => Ok(Walk::Continue),
+            }
+        }
+    }
+
+    #[test]
+    fn walk_mut_keeps_the_tree_when_stopped() {
+        let source = "<a><b><c/><stop/><d/></b><e/></a>";
+        let mut element = parse(source).unwrap();
+
+        assert_eq!(element.walk_mut(&mut Stop), Err(()));
+        assert_eq!(element.to_string(), source);
+    }
+
+    /// Counts the elements and the leaves.
+    #[derive(Default)]
+    struct Count(usize, usize);
+
+    impl<'a, 's> Visitor<'a, &'s str> for Count {
+        type Error = std::convert::Infallible;
+
+        fn leave_element(&mut self, element: &'a ElementRef<'s>) -> Result<(), Self::Error> {
+            self.0 += 1;
+            self.1 += usize::from(element.children.is_empty());
+            Ok(())
+        }
+    }
+
+    impl<'s> VisitorMut<&'s str> for Count {
+        type Error = std::convert::Infallible;
+
+        fn leave_element(&mut self, element: &mut ElementRef<'s>) -> Result<(), Self::Error> {
+            self.0 += 1;
+            self.1 += usize::from(element.children.is_empty());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn walks_do_not_recurse() {
+        let depth = 100_000;
+        let mut element = ElementRef::new("a");
+        for _ in 1..depth {
+            element = ElementRef::new("a").with_child(element);
+        }
+
+        let mut count = Count::default();
+        element.walk(&mut count).unwrap();
+        element.walk_mut(&mut count).unwrap();
+        assert_eq!((count.0, count.1), (2 * depth, 2));
+
+        // dropping is recursive, so take the tree apart first
+        while let Some(child) = element.children.pop() {
+            element = child;
+        }
+    }
+}